@@ -1,54 +1,191 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use ink::prelude::vec::Vec;
-use ink::storage::Mapping;
+use ink::storage::{Lazy, Mapping};
+
+pub use self::bonding_curve_presale::{
+    BondingCurvePresale,
+    BondingCurvePresaleRef,
+};
 
 #[ink::contract]
 mod bonding_curve_presale {
     use super::*;
-    /// Defines the storage of your contract.
-    /// Add new fields to the below struct in order
-    /// to add new static storage fields to your contract.
+    use erc20::Erc20Ref;
+    use ink::env::call::FromAccountId;
 
     const DECIMALS: u128 = 1e18 as u128;
+    // Tokens sell at `BASE_PRICE` for now: the price does not follow the bonding
+    // curve yet, and the creator lock is not implemented either.
+    #[allow(dead_code)]
     const PRICE_CHANGE_SLOPE: u128 = 0.01e18 as u128;
     const BASE_PRICE: u128 = 0.01e18 as u128;
+    #[allow(dead_code)]
     const LOCK_PERIOD: u128 = 6 * 30 * 24 * 60 * 60; // 6 months in seconds
+    #[allow(dead_code)]
     const LOCK_PERCENTAGE: u128 = 10e16 as u128; // 10%
+    const MAX_SUCCESSFUL_END_FEE: u128 = 10e16 as u128; // 10%
+    const KEEPER_REWARD_SHARE: u128 = 10e16 as u128; // 10% of the platform fee
+    /// Most project ids a single `finalizable_projects` call looks at.
+    const MAX_SCANNED_PROJECTS: u32 = 100;
+    /// Presale storage layout this code expects. Version 2 moved projects into
+    /// `versioned_projects`, tagged with the layout they were written in.
+    #[cfg(not(feature = "next-storage-version"))]
+    const STORAGE_VERSION: u32 = 2;
+    /// The upgrade test build expects a version 3 that changes nothing, so
+    /// upgrading to it goes through `migrate` like a real release would.
+    #[cfg(feature = "next-storage-version")]
+    const STORAGE_VERSION: u32 = 3;
 
+    /// The presale holds contributions and deposited tokens of running
+    /// projects, so it cannot be redeployed and is upgraded with `upgrade`
+    /// instead. The packed root therefore keeps its original fields; anything
+    /// added since is a `Mapping` or `Lazy` with its own storage key.
     #[ink(storage)]
     pub struct BondingCurvePresale {
-        /// Stores a single `bool` value on the storage.
-        projects: Mapping<u32, Project>,
+        /// Untagged project entries written before storage version 2. They are
+        /// moved into `versioned_projects` by `migrate_projects` or on their next
+        /// write, and are only read as a fallback until then.
+        projects: Mapping<u32, ProjectV1>,
+        /// Projects tagged with the layout they were written in.
+        versioned_projects: Mapping<u32, VersionedProject>,
+        /// Highest project id `migrate_projects` has moved out of `projects`.
+        migrated_up_to: Lazy<u32>,
         last_project_id: u32,
-        tokens_owed_to_contributor: Mapping<(u128, AccountId), u128>,
+        tokens_owed_to_contributor: Mapping<(u32, AccountId), u128>,
+        /// Native value each contributor paid into a project, returned on refund.
+        contributions: Mapping<(u32, AccountId), Balance>,
         fee_collector: AccountId,
         successful_end_fee: u128,
+        /// Platform admin allowed to pause presales and fail projects.
+        owner: AccountId,
+        /// Account nominated by `transfer_ownership`, until it accepts.
+        pending_owner: Option<AccountId>,
+        /// When set, no new presales can be created.
+        presales_paused: bool,
+        /// Projects on which buys and sells are currently halted.
+        paused_projects: Mapping<u32, ()>,
+        /// Layout version of the data in storage, see `STORAGE_VERSION`.
+        storage_version: u32,
+        /// Native value held for each project until it is paid out or refunded.
+        project_funds: Mapping<u32, Balance>,
+        /// Set while a message that moves funds or tokens is executing.
+        locked: Lazy<bool>,
+        /// Accounts allowed to join a project during its whitelist phase.
+        whitelisted: Mapping<(u32, AccountId), ()>,
+        /// Contract allowed to register projects whose tokens it has already
+        /// deposited here, i.e. the token factory.
+        launcher: Lazy<Option<AccountId>>,
+        /// Cancelled or failed projects whose creator took the deposited
+        /// tokens back.
+        reclaimed_deposits: Mapping<u32, ()>,
     }
 
-    #[derive(Clone)]
+    /// The presale error types.
+    #[derive(Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Error {
+        /// Returned if the caller is not the platform admin.
+        NotOwner,
+        /// Returned if `set_code_hash` failed.
+        UpgradeFailed,
+        /// Returned if `migrate` is called on storage that is already current.
+        StorageUpToDate,
+    }
+
+    /// The presale result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    #[derive(Clone, PartialEq, Eq)]
     #[cfg_attr(
         feature = "std",
-        derive(Debug, PartialEq, Eq, ink::storage::traits::StorageLayout)
+        derive(Debug, ink::storage::traits::StorageLayout)
     )]
     #[ink::scale_derive(Encode, Decode, TypeInfo)]
     pub enum ProjectStatus {
         Pending,
         Success,
         Failed,
+        Cancelled,
+    }
+
+    /// Lifecycle of a presale as seen by its users, derived from the stored
+    /// `ProjectStatus` and the current time by `compute_status`.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(Debug))]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum PresaleStatus {
+        /// Before `start_time`.
+        Upcoming,
+        /// Only whitelisted accounts may join, until `public_start_time`.
+        Whitelist,
+        /// Anyone may join, until `end_time`.
+        Public,
+        /// `end_time` has passed but the presale was not settled yet.
+        Ended,
+        Succeeded,
+        Failed,
+        Cancelled,
+    }
+
+    impl PresaleStatus {
+        /// Whether a message may record a move from this status to `next`.
+        ///
+        /// Moves between `Upcoming`, `Whitelist`, `Public` and `Ended` happen
+        /// by the clock alone and are not listed here.
+        pub fn can_transition_to(self, next: PresaleStatus) -> bool {
+            use PresaleStatus::*;
+            matches!(
+                (self, next),
+                (Upcoming, Cancelled)
+                    | (Upcoming | Whitelist | Public | Ended, Failed)
+                    | (Ended, Succeeded)
+            )
+        }
+
+        /// Whether the outcome of the presale is final.
+        pub fn is_settled(self) -> bool {
+            matches!(
+                self,
+                PresaleStatus::Succeeded | PresaleStatus::Failed | PresaleStatus::Cancelled
+            )
+        }
+    }
+
+    /// First project layout, also used by the untagged legacy entries.
+    #[derive(Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, Eq, ink::storage::traits::StorageLayout)
+    )]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct ProjectV1 {
+        token: AccountId,
+        initial_token_amount: Balance,
+        raised: Balance,
+        start_time: u64,
+        end_time: u64,
+        creator: AccountId,
+        contributors: Vec<AccountId>,
+        status: ProjectStatus,
+        price_after_failure: u64,
+        creator_claimed_locked_tokens: bool,
     }
 
+    /// Second project layout, adding a whitelist phase before the public sale.
     #[derive(Clone)]
     #[cfg_attr(
         feature = "std",
         derive(Debug, PartialEq, Eq, ink::storage::traits::StorageLayout)
     )]
     #[ink::scale_derive(Encode, Decode, TypeInfo)]
-    pub struct Project {
+    pub struct ProjectV2 {
         token: AccountId,
         initial_token_amount: Balance,
         raised: Balance,
         start_time: u64,
+        /// Equal to `start_time` when the project has no whitelist phase.
+        public_start_time: u64,
         end_time: u64,
         creator: AccountId,
         contributors: Vec<AccountId>,
@@ -57,20 +194,513 @@ mod bonding_curve_presale {
         creator_claimed_locked_tokens: bool,
     }
 
+    impl From<ProjectV1> for ProjectV2 {
+        fn from(project: ProjectV1) -> Self {
+            Self {
+                token: project.token,
+                initial_token_amount: project.initial_token_amount,
+                raised: project.raised,
+                start_time: project.start_time,
+                public_start_time: project.start_time,
+                end_time: project.end_time,
+                creator: project.creator,
+                contributors: project.contributors,
+                status: project.status,
+                price_after_failure: project.price_after_failure,
+                creator_claimed_locked_tokens: project.creator_claimed_locked_tokens,
+            }
+        }
+    }
+
+    /// The latest project layout, which every message reads and writes.
+    pub type Project = ProjectV2;
+
+    /// A project together with the layout it was stored in.
+    ///
+    /// Changing `Project` means freezing its current layout under a new
+    /// `ProjectVn` struct and adding a variant here, so that entries written by
+    /// older code keep decoding and are upgraded when they are read.
+    #[derive(Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, Eq, ink::storage::traits::StorageLayout)
+    )]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum VersionedProject {
+        V1(ProjectV1),
+        V2(ProjectV2),
+    }
+
+    impl VersionedProject {
+        /// Upgrades the stored entry to the latest layout.
+        pub fn into_latest(self) -> Project {
+            match self {
+                VersionedProject::V1(project) => project.into(),
+                VersionedProject::V2(project) => project,
+            }
+        }
+    }
+
+    impl From<Project> for VersionedProject {
+        fn from(project: Project) -> Self {
+            VersionedProject::V2(project)
+        }
+    }
+
 
     impl BondingCurvePresale {
-        /// Constructor that initializes the `bool` value to the given `init_value`.
+        /// Creates the presale contract with the caller as platform admin.
         #[ink(constructor)]
         pub fn new(fee_collector: AccountId, successful_end_fee: u128) -> Self {
+            assert!(
+                successful_end_fee <= MAX_SUCCESSFUL_END_FEE,
+                "Fee exceeds the maximum."
+            );
             Self {
                 projects: Mapping::new(),
+                versioned_projects: Mapping::new(),
+                migrated_up_to: Lazy::new(),
                 last_project_id: 0,
                 tokens_owed_to_contributor: Mapping::new(),
+                contributions: Mapping::new(),
                 fee_collector,
                 successful_end_fee,
+                owner: Self::env().caller(),
+                pending_owner: None,
+                presales_paused: false,
+                paused_projects: Mapping::new(),
+                storage_version: STORAGE_VERSION,
+                project_funds: Mapping::new(),
+                locked: Lazy::new(),
+                whitelisted: Mapping::new(),
+                launcher: Lazy::new(),
+                reclaimed_deposits: Mapping::new(),
+            }
+        }
+
+        /// Returns the project stored under `project_id`, if any.
+        #[ink(message)]
+        pub fn get_project(&self, project_id: u32) -> Option<Project> {
+            self.load_project(project_id)
+        }
+
+        /// Returns the layout version of the data in storage.
+        #[ink(message)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version
+        }
+
+        /// Replaces the code of this contract while keeping its storage.
+        ///
+        /// When the new code bumps `STORAGE_VERSION`, the owner must call
+        /// `migrate` right after the upgrade.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the platform admin.
+        ///
+        /// Returns `UpgradeFailed` error if no code is stored under `code_hash`.
+        #[ink(message)]
+        pub fn upgrade(&mut self, code_hash: Hash) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner)
+            }
+            self.env()
+                .set_code_hash(&code_hash)
+                .map_err(|_| Error::UpgradeFailed)?;
+            self.env().emit_event(Upgraded { code_hash });
+            Ok(())
+        }
+
+        /// Brings the data in storage up to the layout of the running code.
+        ///
+        /// A `Migrated` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the platform admin.
+        ///
+        /// Returns `StorageUpToDate` error if there is nothing to migrate.
+        #[ink(message)]
+        pub fn migrate(&mut self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner)
+            }
+            let from_version = self.storage_version;
+            if from_version >= STORAGE_VERSION {
+                return Err(Error::StorageUpToDate)
+            }
+
+            // Each layout change adds a step here, e.g.
+            // `if self.storage_version == 2 { ...; self.storage_version = 3; }`
+            //
+            // Version 2 only adds `versioned_projects`; the project entries
+            // themselves are moved by `migrate_projects`.
+
+            self.storage_version = STORAGE_VERSION;
+            self.env().emit_event(Migrated {
+                from_version,
+                to_version: STORAGE_VERSION,
+            });
+            Ok(())
+        }
+
+        /// Moves up to `batch` projects written before storage version 2 into
+        /// `versioned_projects`, continuing where the previous call stopped.
+        ///
+        /// Returns how many project ids are still left to check.
+        #[ink(message)]
+        pub fn migrate_projects(&mut self, batch: u32) -> u32 {
+            self.ensure_owner();
+            let from_id = self.migrated_up_to.get().unwrap_or(0);
+            let to_id = from_id.saturating_add(batch).min(self.last_project_id);
+
+            let mut migrated = 0;
+            for id in from_id + 1..=to_id {
+                if let Some(project) = self.projects.take(id) {
+                    self.versioned_projects
+                        .insert(id, &VersionedProject::V1(project));
+                    migrated += 1;
+                }
+            }
+            self.migrated_up_to.set(&to_id);
+
+            self.env().emit_event(ProjectsMigrated {
+                from_id: from_id + 1,
+                to_id,
+                migrated,
+            });
+            self.last_project_id - to_id
+        }
+
+        /// Returns the platform admin.
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        /// Returns the account nominated to become the next platform admin.
+        #[ink(message)]
+        pub fn pending_owner(&self) -> Option<AccountId> {
+            self.pending_owner
+        }
+
+        /// Returns the account receiving platform fees.
+        #[ink(message)]
+        pub fn fee_collector(&self) -> AccountId {
+            self.fee_collector
+        }
+
+        /// Returns the fee taken from successful presales, scaled by `DECIMALS`.
+        #[ink(message)]
+        pub fn successful_end_fee(&self) -> u128 {
+            self.successful_end_fee
+        }
+
+        /// Nominates `new_owner` as platform admin.
+        ///
+        /// The nominee becomes owner only once it calls `accept_ownership`.
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) {
+            self.ensure_owner();
+            self.pending_owner = Some(new_owner);
+            self.env().emit_event(OwnershipTransferStarted {
+                previous_owner: self.owner,
+                new_owner,
+            });
+        }
+
+        /// Completes an ownership transfer started by `transfer_ownership`.
+        #[ink(message)]
+        pub fn accept_ownership(&mut self) {
+            let caller = self.env().caller();
+            assert!(
+                self.pending_owner == Some(caller),
+                "Caller is not the pending owner"
+            );
+            let previous_owner = self.owner;
+            self.owner = caller;
+            self.pending_owner = None;
+            self.env().emit_event(OwnershipTransferred {
+                previous_owner,
+                new_owner: caller,
+            });
+        }
+
+        /// Changes the account receiving platform fees.
+        #[ink(message)]
+        pub fn set_fee_collector(&mut self, new_fee_collector: AccountId) {
+            self.ensure_owner();
+            let old_fee_collector = self.fee_collector;
+            self.fee_collector = new_fee_collector;
+            self.env().emit_event(FeeCollectorChanged {
+                old_fee_collector,
+                new_fee_collector,
+            });
+        }
+
+        /// Changes the fee taken from successful presales.
+        ///
+        /// The fee can never exceed `MAX_SUCCESSFUL_END_FEE`.
+        #[ink(message)]
+        pub fn set_successful_end_fee(&mut self, new_fee: u128) {
+            self.ensure_owner();
+            assert!(new_fee <= MAX_SUCCESSFUL_END_FEE, "Fee exceeds the maximum.");
+            let old_fee = self.successful_end_fee;
+            self.successful_end_fee = new_fee;
+            self.env().emit_event(SuccessfulEndFeeChanged { old_fee, new_fee });
+        }
+
+        /// Returns the contract allowed to call `register_launch`.
+        #[ink(message)]
+        pub fn launcher(&self) -> Option<AccountId> {
+            self.launcher.get().flatten()
+        }
+
+        /// Sets or clears the contract allowed to call `register_launch`.
+        #[ink(message)]
+        pub fn set_launcher(&mut self, launcher: Option<AccountId>) {
+            self.ensure_owner();
+            self.launcher.set(&launcher);
+            self.env().emit_event(LauncherChanged { launcher });
+        }
+
+        /// Returns whether the creation of new presales is paused.
+        #[ink(message)]
+        pub fn presales_paused(&self) -> bool {
+            self.presales_paused
+        }
+
+        /// Returns whether buys and sells on `project_id` are paused.
+        #[ink(message)]
+        pub fn is_project_paused(&self, project_id: u32) -> bool {
+            self.paused_projects.contains(project_id)
+        }
+
+        /// Stops anyone from creating new presales.
+        #[ink(message)]
+        pub fn pause_presales(&mut self) {
+            self.ensure_owner();
+            self.presales_paused = true;
+            self.env().emit_event(PresalesPaused {
+                by: self.env().caller(),
+            });
+        }
+
+        /// Allows new presales to be created again.
+        #[ink(message)]
+        pub fn unpause_presales(&mut self) {
+            self.ensure_owner();
+            self.presales_paused = false;
+            self.env().emit_event(PresalesUnpaused {
+                by: self.env().caller(),
+            });
+        }
+
+        /// Halts buys and sells on a single project.
+        #[ink(message)]
+        pub fn pause_project(&mut self, project_id: u32) {
+            self.ensure_owner();
+            assert!(self.load_project(project_id).is_some(), "Project not found");
+            self.paused_projects.insert(project_id, &());
+            self.env().emit_event(ProjectPaused {
+                project_id,
+                by: self.env().caller(),
+            });
+        }
+
+        /// Resumes buys and sells on a paused project.
+        #[ink(message)]
+        pub fn unpause_project(&mut self, project_id: u32) {
+            self.ensure_owner();
+            assert!(self.load_project(project_id).is_some(), "Project not found");
+            self.paused_projects.remove(project_id);
+            self.env().emit_event(ProjectUnpaused {
+                project_id,
+                by: self.env().caller(),
+            });
+        }
+
+        /// Marks a running presale as failed so every contributor can
+        /// reclaim their contribution through `claim_refund`.
+        #[ink(message)]
+        pub fn emergency_fail(&mut self, project_id: u32) {
+            self.ensure_owner();
+            let project = self.load_project(project_id).expect("Project not found");
+            self.transition(project_id, project, PresaleStatus::Failed);
+
+            self.env().emit_event(ProjectEmergencyFailed {
+                project_id,
+                by: self.env().caller(),
+            });
+        }
+
+        /// Returns the caller's contribution to a failed project.
+        ///
+        /// A project whose presale has ended is settled first.
+        #[ink(message)]
+        pub fn claim_refund(&mut self, project_id: u32) {
+            self.enter();
+            let caller = self.env().caller();
+            self.settle_if_ended(project_id, caller);
+            let project = self.load_project(project_id).expect("Project not found");
+            assert!(project.status == ProjectStatus::Failed, "Presale has not failed");
+
+            let amount = self.contributions.get((project_id, caller)).unwrap_or(0);
+            assert!(amount > 0, "Nothing to refund");
+
+            self.contributions.remove((project_id, caller));
+            self.tokens_owed_to_contributor.remove((project_id, caller));
+            let funds = self.project_funds.get(project_id).unwrap_or(0);
+            self.project_funds
+                .insert(project_id, &funds.saturating_sub(amount));
+
+            self.env()
+                .transfer(caller, amount)
+                .expect("Failed to send refund to the user");
+
+            self.env().emit_event(RefundClaimed {
+                project_id,
+                contributor: caller,
+                amount,
+            });
+            self.exit();
+        }
+
+        /// Sends the caller the tokens they bought in a successful presale.
+        ///
+        /// A project whose presale has ended is settled first.
+        #[ink(message)]
+        pub fn claim_tokens(&mut self, project_id: u32) {
+            self.enter();
+            let caller = self.env().caller();
+            self.settle_if_ended(project_id, caller);
+            let project = self.load_project(project_id).expect("Project not found");
+            assert!(project.status == ProjectStatus::Success, "Presale has not succeeded");
+
+            let amount = self
+                .tokens_owed_to_contributor
+                .get((project_id, caller))
+                .unwrap_or(0);
+            assert!(amount > 0, "No tokens owed to the caller");
+
+            self.tokens_owed_to_contributor.remove((project_id, caller));
+            self.contributions.remove((project_id, caller));
+
+            let mut token: Erc20Ref = FromAccountId::from_account_id(project.token);
+            token
+                .transfer(caller, amount)
+                .expect("Failed to send tokens to the user");
+
+            self.env().emit_event(TokensClaimed {
+                project_id,
+                contributor: caller,
+                amount,
+            });
+            self.exit();
+        }
+
+        /// Sends the tokens deposited for a cancelled or failed presale back to
+        /// its creator. Only the creator can call this, and only once.
+        ///
+        /// A project whose presale has ended is settled first.
+        #[ink(message)]
+        pub fn reclaim_tokens(&mut self, project_id: u32) {
+            self.enter();
+            let caller = self.env().caller();
+            self.settle_if_ended(project_id, caller);
+            let project = self.load_project(project_id).expect("Project not found");
+            assert!(caller == project.creator, "Caller is not the creator");
+            assert!(
+                matches!(project.status, ProjectStatus::Cancelled | ProjectStatus::Failed),
+                "Presale was neither cancelled nor failed"
+            );
+            assert!(
+                !self.reclaimed_deposits.contains(project_id),
+                "Tokens already reclaimed"
+            );
+
+            self.reclaimed_deposits.insert(project_id, &());
+            let amount = project.initial_token_amount;
+            let mut token: Erc20Ref = FromAccountId::from_account_id(project.token);
+            token
+                .transfer(caller, amount)
+                .expect("Failed to return the tokens to the creator");
+
+            self.env().emit_event(TokensReclaimed {
+                project_id,
+                creator: caller,
+                amount,
+            });
+            self.exit();
+        }
+
+        /// Returns where `project_id` is in its lifecycle right now.
+        #[ink(message)]
+        pub fn status_of(&self, project_id: u32) -> Option<PresaleStatus> {
+            let now = self.env().block_timestamp();
+            self.load_project(project_id)
+                .map(|project| Self::compute_status(&project, now))
+        }
+
+        /// Restricts joining to `accounts` until `public_start_time`.
+        ///
+        /// Only the creator can call this, and only before the presale starts.
+        /// Further calls add more accounts and move the public start.
+        #[ink(message)]
+        pub fn configure_whitelist(
+            &mut self,
+            project_id: u32,
+            public_start_time: u64,
+            accounts: Vec<AccountId>,
+        ) {
+            let mut project = self.load_project(project_id).expect("Project not found");
+            assert!(self.env().caller() == project.creator, "Caller is not the creator");
+            assert!(
+                Self::compute_status(&project, self.env().block_timestamp())
+                    == PresaleStatus::Upcoming,
+                "Presale already started"
+            );
+            assert!(
+                project.start_time <= public_start_time && public_start_time <= project.end_time,
+                "Public start must be within the presale."
+            );
+
+            for account in &accounts {
+                self.whitelisted.insert((project_id, *account), &());
             }
+            project.public_start_time = public_start_time;
+            self.save_project(project_id, project);
+
+            self.env().emit_event(WhitelistConfigured {
+                project_id,
+                public_start_time,
+                added: accounts.len() as u32,
+            });
         }
 
+        /// Returns whether `account` may join `project_id` during its
+        /// whitelist phase.
+        #[ink(message)]
+        pub fn is_whitelisted(&self, project_id: u32, account: AccountId) -> bool {
+            self.whitelisted.contains((project_id, account))
+        }
+
+        /// Cancels a presale that has not started yet. Only the creator can
+        /// call this, and takes the deposited tokens back with `reclaim_tokens`.
+        #[ink(message)]
+        pub fn cancel_presale(&mut self, project_id: u32) {
+            let project = self.load_project(project_id).expect("Project not found");
+            assert!(self.env().caller() == project.creator, "Caller is not the creator");
+            self.transition(project_id, project, PresaleStatus::Cancelled);
+            self.env().emit_event(PresaleCancelled { project_id });
+        }
+
+        /// Opens a presale for `initial_token_amount` of the caller's `token`
+        /// and returns the id of the new project.
+        ///
+        /// The tokens are pulled into this contract, so the caller must have
+        /// approved it to spend them first.
         #[ink(message)]
         pub fn create_presale(
             &mut self,
@@ -78,91 +708,123 @@ mod bonding_curve_presale {
             initial_token_amount: u128,
             start_time: u64,
             end_time: u64,
-        ) {
-            assert!(start_time > Self::env().block_timestamp(), "Start time must be in the future.");
-            assert!(end_time > start_time, "End time must be after start time.");
-            assert!(initial_token_amount % 2 == 0, "Initial token amount must be even.");
-
-            self.last_project_id += 1;
-            // create new token here
-
-            ////////////////////////
-            let project = Project {
-                token,
-                initial_token_amount,
-                raised: 0,
-                start_time,
-                end_time,
-                creator: Self::env().caller(),
-                contributors: Vec::new(),
-                status: ProjectStatus::Pending,
-                price_after_failure: 0,
-                creator_claimed_locked_tokens: false,
-            };
-
-            self.projects.insert(self.last_project_id, &project);
+        ) -> u32 {
+            self.enter();
+            let creator = Self::env().caller();
+            let project_id =
+                self.insert_project(token, creator, initial_token_amount, start_time, end_time);
+            self.pull_tokens(token, creator, initial_token_amount);
+            self.exit();
+            project_id
         }
 
+        /// Registers a presale for `creator` whose `initial_token_amount` tokens
+        /// the launcher has already deposited into this contract.
+        ///
+        /// Returns the id of the new project.
         #[ink(message)]
+        pub fn register_launch(
+            &mut self,
+            token: AccountId,
+            creator: AccountId,
+            initial_token_amount: u128,
+            start_time: u64,
+            end_time: u64,
+        ) -> u32 {
+            assert!(
+                self.launcher() == Some(self.env().caller()),
+                "Caller is not the launcher"
+            );
+            self.insert_project(token, creator, initial_token_amount, start_time, end_time)
+        }
+
+        /// Buys tokens of `project_id` with the transferred value, at the rate of
+        /// `calculate_buy_amount`. The part of the payment not needed for the
+        /// tokens left for sale is sent back.
+        #[ink(message, payable)]
         pub fn join_project_presale(
             &mut self,
             project_id: u32,
             expected_token_amount: Balance,
         ) {
+            self.enter();
             let caller = self.env().caller();
-            let mut project = self.projects.get(&project_id).expect("Project not found");
+            let mut project = self.load_project(project_id).expect("Project not found");
             
             // Check various conditions
-            assert!(project.status == ProjectStatus::Pending, "Presale already ended");
-            assert!(project.start_time <= self.env().block_timestamp(), "Presale not started");
-            assert!(project.end_time > self.env().block_timestamp(), "Presale ended");
-
-            // Calculate the token amount and update the project
-            let mut token_amount = self.calculate_buy_amount(project.initial_token_amount, project.raised);
-            
-            // Ensure user is not contributing more than allowed
-            let max_tokens = project.initial_token_amount / 2;
-            let remaining_tokens = max_tokens - project.raised;
-            if token_amount > remaining_tokens {
-                token_amount = remaining_tokens;
+            match Self::compute_status(&project, self.env().block_timestamp()) {
+                PresaleStatus::Public => {}
+                PresaleStatus::Whitelist => assert!(
+                    self.whitelisted.contains((project_id, caller)),
+                    "Caller is not whitelisted"
+                ),
+                PresaleStatus::Upcoming => panic!("Presale not started"),
+                _ => panic!("Presale ended"),
             }
+            assert!(!self.is_project_paused(project_id), "Project is paused");
+
+            // Calculate the token amount the payment buys
+            let value = self.env().transferred_value();
+            let token_amount = self.calculate_buy_amount(project_id, value);
             assert!(token_amount >= expected_token_amount, "Lack of token");
 
+            // Keep only what the tokens cost, the rest is sent back below
+            let cost = Self::mul_div(token_amount, BASE_PRICE, DECIMALS)
+                .expect("Token cost overflows");
+            let refund = value - cost;
+
             // Update the project and contributor's tokens
             project.raised += token_amount;
             if !project.contributors.contains(&caller) {
                 project.contributors.push(caller);
             }
-            // here update contributors tokens
+            self.save_project(project_id, project);
+
+            let owed = self.tokens_owed_to_contributor.get((project_id, caller)).unwrap_or(0);
+            self.tokens_owed_to_contributor
+                .insert((project_id, caller), &(owed + token_amount));
+            let contributed = self.contributions.get((project_id, caller)).unwrap_or(0);
+            self.contributions
+                .insert((project_id, caller), &(contributed + cost));
+            let funds = self.project_funds.get(project_id).unwrap_or(0);
+            self.project_funds.insert(project_id, &(funds + cost));
+
+            if refund > 0 {
+                self.env()
+                    .transfer(caller, refund)
+                    .expect("Failed to send change to the user");
+            }
 
-            //////////////////////////////////
             // Emit event
             self.env().emit_event(UserJoinedProject {
                 project_id,
                 contributor: caller,
                 token_amount,
             });
+            self.exit();
         }
 
         #[ink(message)]
         pub fn leave_ongoing_project_presale(
             &mut self,
             id: u32,
-            expected_eth_amount: Balance,
+            _expected_eth_amount: Balance,
         ) {
+            self.enter();
+
             // Ensure project ID is valid
-            let mut project = self.projects.get(&id).expect("Project not found");
-            
-            // Ensure project status is Pending
-            assert!(project.status == ProjectStatus::Pending, "Presale already ended");
+            let project = self.load_project(id).expect("Project not found");
             
-            // Ensure the project has started
-            assert!(project.start_time <= self.env().block_timestamp(), "Presale not started");
-            
-            // Ensure the project has not ended
-            assert!(project.end_time > self.env().block_timestamp(), "Presale ended");
+            // Ensure the presale is running
+            match Self::compute_status(&project, self.env().block_timestamp()) {
+                PresaleStatus::Whitelist | PresaleStatus::Public => {}
+                PresaleStatus::Upcoming => panic!("Presale not started"),
+                _ => panic!("Presale ended"),
+            }
+
+            // Ensure sells are not halted on this project
+            assert!(!self.is_project_paused(id), "Project is paused");
 
-            
             // Get the token amount owed to the caller
             // let caller = self.env().caller();
             // let token_amount = *self.tokens_owed_to_contributor.get(&(id, caller)).unwrap_or(&0);
@@ -206,48 +868,250 @@ mod bonding_curve_presale {
             //     token_amount,
             //     eth_amount,
             // });
-            
+
+            self.exit();
         }
 
+        /// Settles a presale once it has ended.
+        ///
+        /// Anyone may call this. On success the platform fee goes to
+        /// `fee_collector`, minus a `KEEPER_REWARD_SHARE` tip for the caller,
+        /// and the rest of the raised funds to the creator; on failure the funds
+        /// stay in the contract for `claim_refund`.
         #[ink(message)]
         pub fn end_presale(&mut self, id: u32) {
-            let project = self.projects.get(&id).expect("Project does not exist.");
-            assert!(
-                project.end_time < Self::env().block_timestamp(),
-                "Project has not ended yet."
-            );
+            self.enter();
+            let project = self.load_project(id).expect("Project does not exist.");
+            let status = Self::compute_status(&project, Self::env().block_timestamp());
+            assert!(!status.is_settled(), "Presale already ended");
+            assert!(status == PresaleStatus::Ended, "Project has not ended yet.");
 
-            let is_successful = project.raised >= self.get_soft_cap(id);
-            let mut updated_project = project.clone();
-            updated_project.status = if is_successful {
-                ProjectStatus::Success
-            } else {
-                ProjectStatus::Failed
-            };
+            self.finalize(id, project, self.env().caller());
+            self.exit();
+        }
 
-            if is_successful {
-                // Logic for successful presale
-            } else {
-                // Logic for failed presale
-            }
-
-            self.projects.insert(&id, &updated_project);
+        /// Returns the ids of presales that have ended but were not settled yet
+        /// among the `limit` project ids starting at `from_id`, lowest id first,
+        /// together with the id to pass as `from_id` next.
+        ///
+        /// At most `MAX_SCANNED_PROJECTS` ids are looked at per call, so keepers
+        /// walk all projects by following the returned cursor until it is
+        /// `None`.
+        #[ink(message)]
+        pub fn finalizable_projects(&self, from_id: u32, limit: u32) -> (Vec<u32>, Option<u32>) {
+            let now = self.env().block_timestamp();
+            let from_id = from_id.max(1);
+            let end_id = from_id
+                .saturating_add(limit.min(MAX_SCANNED_PROJECTS))
+                .min(self.last_project_id.saturating_add(1));
+            let ids = (from_id..end_id)
+                .filter(|&id| {
+                    self.load_project(id).is_some_and(|project| {
+                        Self::compute_status(&project, now) == PresaleStatus::Ended
+                    })
+                })
+                .collect();
+            (ids, (end_id <= self.last_project_id).then_some(end_id))
         }
 
+        /// Returns how many tokens of `project_id` a payment of `value` buys.
+        ///
+        /// Tokens cost `BASE_PRICE` per whole token, and at most half of the
+        /// initial supply is sold, so a payment past that only buys what is
+        /// left. `join_project_presale` sends the rest of it back.
         #[ink(message)]
-        pub fn calculate_buy_amount(
-            &self,
-            total_supply: Balance,
-            total_raised: Balance,
-        ) -> Balance {
-            // Implement bonding curve logic here, similar to Solidity's
-            total_raised * 10u128.pow(18) / total_supply
+        pub fn calculate_buy_amount(&self, project_id: u32, value: Balance) -> Balance {
+            let project = self.load_project(project_id).expect("Project not found");
+            let max_tokens = project.initial_token_amount / 2;
+            let remaining_tokens = max_tokens - project.raised;
+            Self::mul_div(value, DECIMALS, BASE_PRICE)
+                .unwrap_or(Balance::MAX)
+                .min(remaining_tokens)
+        }
+
+        fn insert_project(
+            &mut self,
+            token: AccountId,
+            creator: AccountId,
+            initial_token_amount: u128,
+            start_time: u64,
+            end_time: u64,
+        ) -> u32 {
+            assert!(!self.presales_paused, "Presales are paused.");
+            assert!(start_time > Self::env().block_timestamp(), "Start time must be in the future.");
+            assert!(end_time > start_time, "End time must be after start time.");
+            assert!(initial_token_amount.is_multiple_of(2), "Initial token amount must be even.");
+
+            self.last_project_id += 1;
+            let project = Project {
+                token,
+                initial_token_amount,
+                raised: 0,
+                start_time,
+                public_start_time: start_time,
+                end_time,
+                creator,
+                contributors: Vec::new(),
+                status: ProjectStatus::Pending,
+                price_after_failure: 0,
+                creator_claimed_locked_tokens: false,
+            };
+
+            self.save_project(self.last_project_id, project);
+            self.last_project_id
         }
 
-        fn get_soft_cap(&self, id: u32) -> u128 {
-            let project = self.projects.get(&id).expect("Project does not exist.");
+        /// Moves `amount` of `token` from `from` to this contract, checking that
+        /// the contract's balance grew by at least `amount`.
+        fn pull_tokens(&self, token: AccountId, from: AccountId, amount: Balance) {
+            let mut token: Erc20Ref = FromAccountId::from_account_id(token);
+            let this = self.env().account_id();
+            let balance_before = token.balance_of(this);
+            token
+                .transfer_from(from, this, amount)
+                .expect("Failed to pull the presale tokens");
+            assert!(
+                token.balance_of(this).saturating_sub(balance_before) >= amount,
+                "Presale tokens were not received"
+            );
+        }
+
+        fn get_soft_cap(project: &Project) -> u128 {
             project.initial_token_amount * 3 / 10 //30%
         }
+
+        /// Settles `id` on behalf of `keeper` if its presale has ended.
+        fn settle_if_ended(&mut self, id: u32, keeper: AccountId) {
+            let project = self.load_project(id).expect("Project not found");
+            if Self::compute_status(&project, self.env().block_timestamp())
+                == PresaleStatus::Ended
+            {
+                self.finalize(id, project, keeper);
+            }
+        }
+
+        /// Records the outcome of an ended presale and pays out a successful one.
+        ///
+        /// Storage is updated before any funds move.
+        fn finalize(&mut self, id: u32, project: Project, keeper: AccountId) {
+            let is_successful = project.raised >= Self::get_soft_cap(&project);
+            let creator = project.creator;
+            self.transition(
+                id,
+                project,
+                if is_successful {
+                    PresaleStatus::Succeeded
+                } else {
+                    PresaleStatus::Failed
+                },
+            );
+
+            let mut keeper_reward = 0;
+            if is_successful {
+                let funds = self.project_funds.take(id).unwrap_or(0);
+                let fee = Self::mul_div(funds, self.successful_end_fee, DECIMALS)
+                    .expect("Platform fee overflows");
+                keeper_reward = Self::mul_div(fee, KEEPER_REWARD_SHARE, DECIMALS)
+                    .expect("Keeper reward overflows");
+                if keeper_reward > 0 {
+                    self.env()
+                        .transfer(keeper, keeper_reward)
+                        .expect("Failed to send the keeper reward");
+                }
+                if fee > keeper_reward {
+                    self.env()
+                        .transfer(self.fee_collector, fee - keeper_reward)
+                        .expect("Failed to send the platform fee");
+                }
+                self.env()
+                    .transfer(creator, funds - fee)
+                    .expect("Failed to send raised funds to the creator");
+            }
+
+            self.env().emit_event(PresaleFinalized {
+                project_id: id,
+                keeper,
+                successful: is_successful,
+                keeper_reward,
+            });
+        }
+
+        /// Returns `value * numerator / denominator` rounded down, or `None` if
+        /// it does not fit in a `u128`.
+        ///
+        /// `value` is split into a multiple of `denominator` and a remainder, so
+        /// neither product exceeds the result or `numerator * denominator`, and
+        /// shares of at most `DECIMALS` never overflow.
+        fn mul_div(value: u128, numerator: u128, denominator: u128) -> Option<u128> {
+            let (whole, remainder) = (value / denominator, value % denominator);
+            whole
+                .checked_mul(numerator)
+                .zip(remainder.checked_mul(numerator))
+                .and_then(|(whole, part)| whole.checked_add(part / denominator))
+        }
+
+        /// Derives the lifecycle status of `project` at time `now`.
+        ///
+        /// Each phase starts at its timestamp inclusive, so a presale is
+        /// `Ended` from `end_time` on.
+        fn compute_status(project: &Project, now: u64) -> PresaleStatus {
+            match project.status {
+                ProjectStatus::Success => PresaleStatus::Succeeded,
+                ProjectStatus::Failed => PresaleStatus::Failed,
+                ProjectStatus::Cancelled => PresaleStatus::Cancelled,
+                ProjectStatus::Pending if now < project.start_time => PresaleStatus::Upcoming,
+                ProjectStatus::Pending if now < project.public_start_time => {
+                    PresaleStatus::Whitelist
+                }
+                ProjectStatus::Pending if now < project.end_time => PresaleStatus::Public,
+                ProjectStatus::Pending => PresaleStatus::Ended,
+            }
+        }
+
+        /// Records the move of `project` to `next`, enforcing
+        /// `PresaleStatus::can_transition_to`.
+        fn transition(&mut self, id: u32, mut project: Project, next: PresaleStatus) {
+            let current = Self::compute_status(&project, self.env().block_timestamp());
+            assert!(current.can_transition_to(next), "Invalid status transition");
+            project.status = match next {
+                PresaleStatus::Succeeded => ProjectStatus::Success,
+                PresaleStatus::Failed => ProjectStatus::Failed,
+                PresaleStatus::Cancelled => ProjectStatus::Cancelled,
+                _ => panic!("Invalid status transition"),
+            };
+            self.save_project(id, project);
+        }
+
+        /// Reads a project, upgrading it to the latest layout.
+        fn load_project(&self, id: u32) -> Option<Project> {
+            self.versioned_projects
+                .get(id)
+                .map(VersionedProject::into_latest)
+                .or_else(|| self.projects.get(id).map(Project::from))
+        }
+
+        /// Writes a project in the latest layout, dropping any legacy entry.
+        fn save_project(&mut self, id: u32, project: Project) {
+            self.versioned_projects
+                .insert(id, &VersionedProject::from(project));
+            self.projects.remove(id);
+        }
+
+        /// Takes the reentrancy lock, rejecting calls made while another
+        /// message is still paying out.
+        fn enter(&mut self) {
+            assert!(!self.locked.get().unwrap_or(false), "Reentrant call");
+            self.locked.set(&true);
+        }
+
+        /// Releases the reentrancy lock taken by `enter`.
+        fn exit(&mut self) {
+            self.locked.set(&false);
+        }
+
+        fn ensure_owner(&self) {
+            assert!(self.env().caller() == self.owner, "Caller is not the owner");
+        }
     }
 
     #[ink(event)]
@@ -269,6 +1133,147 @@ mod bonding_curve_presale {
         eth_amount: Balance
     }
 
+    #[ink(event)]
+    pub struct WhitelistConfigured {
+        #[ink(topic)]
+        project_id: u32,
+        public_start_time: u64,
+        added: u32,
+    }
+
+    #[ink(event)]
+    pub struct PresaleCancelled {
+        #[ink(topic)]
+        project_id: u32,
+    }
+
+    #[ink(event)]
+    pub struct PresaleFinalized {
+        #[ink(topic)]
+        project_id: u32,
+        #[ink(topic)]
+        keeper: AccountId,
+        successful: bool,
+        keeper_reward: Balance,
+    }
+
+    #[ink(event)]
+    pub struct TokensClaimed {
+        #[ink(topic)]
+        project_id: u32,
+        #[ink(topic)]
+        contributor: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct TokensReclaimed {
+        #[ink(topic)]
+        project_id: u32,
+        #[ink(topic)]
+        creator: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        #[ink(topic)]
+        code_hash: Hash,
+    }
+
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    #[ink(event)]
+    pub struct ProjectsMigrated {
+        from_id: u32,
+        to_id: u32,
+        migrated: u32,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipTransferStarted {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct FeeCollectorChanged {
+        #[ink(topic)]
+        old_fee_collector: AccountId,
+        #[ink(topic)]
+        new_fee_collector: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct SuccessfulEndFeeChanged {
+        old_fee: u128,
+        new_fee: u128,
+    }
+
+    #[ink(event)]
+    pub struct LauncherChanged {
+        launcher: Option<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct PresalesPaused {
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct PresalesUnpaused {
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ProjectPaused {
+        #[ink(topic)]
+        project_id: u32,
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ProjectUnpaused {
+        #[ink(topic)]
+        project_id: u32,
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ProjectEmergencyFailed {
+        #[ink(topic)]
+        project_id: u32,
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct RefundClaimed {
+        #[ink(topic)]
+        project_id: u32,
+        #[ink(topic)]
+        contributor: AccountId,
+        amount: Balance,
+    }
+
     /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
     /// module and test functions are marked with a `#[test]` attribute.
     /// The below code is technically just normal Rust code.
@@ -277,6 +1282,991 @@ mod bonding_curve_presale {
         /// Imports all the definitions from the outer scope so we can use them here.
         use super::*;
 
-       
+        type Env = ink::env::DefaultEnvironment;
+
+        const START: u64 = 1_000;
+        const END: u64 = 2_000;
+
+        fn accounts() -> ink::env::test::DefaultAccounts<Env> {
+            ink::env::test::default_accounts::<Env>()
+        }
+
+        fn set_caller(caller: AccountId) {
+            ink::env::test::set_caller::<Env>(caller);
+        }
+
+        fn set_now(timestamp: u64) {
+            ink::env::test::set_block_timestamp::<Env>(timestamp);
+        }
+
+        /// Whole tokens launched by `launch`.
+        const SUPPLY: Balance = 1_000 * DECIMALS;
+
+        /// Registers a project of `SUPPLY` tokens for `creator` starting at
+        /// `START` the way the token factory does, with its tokens already
+        /// deposited, so no token contract is called. Alice acts as the launcher.
+        fn launch(presale: &mut BondingCurvePresale, creator: AccountId, end_time: u64) -> u32 {
+            set_caller(accounts().alice);
+            presale.set_launcher(Some(accounts().alice));
+            presale.register_launch(accounts().frank, creator, SUPPLY, START, end_time)
+        }
+
+        /// Deploys the presale as Alice and launches one project of hers.
+        fn presale_with_project() -> BondingCurvePresale {
+            let accounts = accounts();
+            set_caller(accounts.alice);
+            set_now(0);
+            let mut presale = BondingCurvePresale::new(accounts.eve, 0);
+            launch(&mut presale, accounts.alice, END);
+            presale
+        }
+
+        #[ink::test]
+        fn deployer_is_owner() {
+            let presale = presale_with_project();
+            assert_eq!(presale.owner(), accounts().alice);
+            assert!(!presale.presales_paused());
+            assert!(!presale.is_project_paused(1));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Caller is not the owner")]
+        fn only_owner_can_pause_presales() {
+            let mut presale = presale_with_project();
+            set_caller(accounts().bob);
+            presale.pause_presales();
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Presales are paused.")]
+        fn paused_presales_reject_new_projects() {
+            let mut presale = presale_with_project();
+            presale.pause_presales();
+            presale.create_presale(accounts().frank, 1_000, START, END);
+        }
+
+        #[ink::test]
+        fn unpaused_presales_accept_new_projects() {
+            let mut presale = presale_with_project();
+            presale.pause_presales();
+            presale.unpause_presales();
+            launch(&mut presale, accounts().alice, END);
+            assert_eq!(presale.last_project_id, 2);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Project is paused")]
+        fn paused_project_rejects_joins() {
+            let mut presale = presale_with_project();
+            presale.pause_project(1);
+            set_now(START);
+            set_caller(accounts().bob);
+            presale.join_project_presale(1, 0);
+        }
+
+        #[ink::test]
+        fn emergency_fail_refunds_contributors() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            let contract = ink::env::test::callee::<Env>();
+            ink::env::test::set_account_balance::<Env>(contract, 0);
+            ink::env::test::set_account_balance::<Env>(accounts.bob, 100_000_000);
+
+            set_now(START);
+            set_caller(accounts.bob);
+            ink::env::test::transfer_in::<Env>(40_000_000);
+            presale.join_project_presale(1, 0);
+            assert_eq!(presale.contributions.get((1, accounts.bob)), Some(40_000_000));
+
+            set_caller(accounts.alice);
+            presale.emergency_fail(1);
+            assert_eq!(
+                presale.get_project(1).unwrap().status,
+                ProjectStatus::Failed
+            );
+
+            set_caller(accounts.bob);
+            presale.claim_refund(1);
+            assert_eq!(
+                ink::env::test::get_account_balance::<Env>(accounts.bob),
+                Ok(100_000_000)
+            );
+            assert_eq!(presale.contributions.get((1, accounts.bob)), None);
+        }
+
+        #[ink::test]
+        fn owner_updates_fee_settings() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            presale.set_fee_collector(accounts.django);
+            presale.set_successful_end_fee(MAX_SUCCESSFUL_END_FEE);
+            assert_eq!(presale.fee_collector(), accounts.django);
+            assert_eq!(presale.successful_end_fee(), MAX_SUCCESSFUL_END_FEE);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Fee exceeds the maximum.")]
+        fn fee_above_maximum_is_rejected() {
+            let mut presale = presale_with_project();
+            presale.set_successful_end_fee(MAX_SUCCESSFUL_END_FEE + 1);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Caller is not the owner")]
+        fn only_owner_can_change_fee_collector() {
+            let mut presale = presale_with_project();
+            set_caller(accounts().bob);
+            presale.set_fee_collector(accounts().bob);
+        }
+
+        #[ink::test]
+        fn ownership_transfer_takes_two_steps() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            presale.transfer_ownership(accounts.bob);
+            assert_eq!(presale.owner(), accounts.alice);
+            assert_eq!(presale.pending_owner(), Some(accounts.bob));
+
+            set_caller(accounts.bob);
+            presale.accept_ownership();
+            assert_eq!(presale.owner(), accounts.bob);
+            assert_eq!(presale.pending_owner(), None);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Caller is not the pending owner")]
+        fn only_pending_owner_can_accept() {
+            let mut presale = presale_with_project();
+            presale.transfer_ownership(accounts().bob);
+            set_caller(accounts().charlie);
+            presale.accept_ownership();
+        }
+
+        #[ink::test]
+        fn only_owner_can_upgrade() {
+            let mut presale = presale_with_project();
+            set_caller(accounts().bob);
+            assert_eq!(presale.upgrade(Hash::from([0x42; 32])), Err(Error::NotOwner));
+            assert_eq!(presale.migrate(), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn migrate_on_current_storage_fails() {
+            let mut presale = presale_with_project();
+            assert_eq!(presale.migrate(), Err(Error::StorageUpToDate));
+        }
+
+        #[ink::test]
+        fn migrate_bumps_storage_version() {
+            let mut presale = presale_with_project();
+            let project = presale.get_project(1);
+            presale.storage_version = 0;
+
+            assert_eq!(presale.migrate(), Ok(()));
+
+            assert_eq!(presale.storage_version(), STORAGE_VERSION);
+            assert_eq!(presale.get_project(1), project);
+        }
+
+        /// Freezes `project` in the layout written before storage version 2.
+        fn as_v1(project: Project) -> ProjectV1 {
+            ProjectV1 {
+                token: project.token,
+                initial_token_amount: project.initial_token_amount,
+                raised: project.raised,
+                start_time: project.start_time,
+                end_time: project.end_time,
+                creator: project.creator,
+                contributors: project.contributors,
+                status: project.status,
+                price_after_failure: project.price_after_failure,
+                creator_claimed_locked_tokens: project.creator_claimed_locked_tokens,
+            }
+        }
+
+        #[ink::test]
+        fn legacy_projects_are_readable_and_migrated_in_batches() {
+            let mut presale = presale_with_project();
+            let project = presale.get_project(1).unwrap();
+            // Simulate three entries written before storage version 2.
+            for id in 1..=3 {
+                presale.versioned_projects.remove(id);
+                presale.projects.insert(id, &as_v1(project.clone()));
+            }
+            presale.last_project_id = 3;
+            assert_eq!(presale.get_project(2), Some(project.clone()));
+
+            assert_eq!(presale.migrate_projects(2), 1);
+            assert!(presale.projects.get(1).is_none());
+            assert!(presale.projects.get(2).is_none());
+            assert!(presale.projects.get(3).is_some());
+
+            assert_eq!(presale.migrate_projects(2), 0);
+            for id in 1..=3 {
+                assert!(presale.projects.get(id).is_none());
+                assert_eq!(
+                    presale.versioned_projects.get(id),
+                    Some(VersionedProject::V1(as_v1(project.clone())))
+                );
+                assert_eq!(presale.get_project(id), Some(project.clone()));
+            }
+        }
+
+        #[ink::test]
+        fn writes_replace_legacy_entries() {
+            let mut presale = presale_with_project();
+            let project = presale.versioned_projects.take(1).unwrap().into_latest();
+            presale.projects.insert(1, &as_v1(project));
+
+            presale.emergency_fail(1);
+
+            assert!(presale.projects.get(1).is_none());
+            assert_eq!(
+                presale.get_project(1).unwrap().status,
+                ProjectStatus::Failed
+            );
+        }
+
+        /// Joins project 1 as `contributor`, paying `value`.
+        fn join(presale: &mut BondingCurvePresale, contributor: AccountId, value: Balance) {
+            ink::env::test::set_account_balance::<Env>(contributor, value);
+            set_caller(contributor);
+            ink::env::test::transfer_in::<Env>(value);
+            presale.join_project_presale(1, 0);
+        }
+
+        #[ink::test]
+        fn refund_cannot_be_claimed_twice() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            ink::env::test::set_account_balance::<Env>(ink::env::test::callee::<Env>(), 0);
+            set_now(START);
+            join(&mut presale, accounts.bob, 40_000_000);
+            join(&mut presale, accounts.charlie, 60_000_000);
+            set_caller(accounts.alice);
+            presale.emergency_fail(1);
+
+            set_caller(accounts.bob);
+            presale.claim_refund(1);
+            assert_eq!(presale.project_funds.get(1), Some(60_000_000));
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                presale.claim_refund(1)
+            }));
+            assert!(result.is_err(), "second refund must be rejected");
+            assert_eq!(
+                ink::env::test::get_account_balance::<Env>(accounts.bob),
+                Ok(40_000_000)
+            );
+        }
+
+        /// Holds the lock as a paying message does while the token runs, and
+        /// checks the guard of each message on its own. A token actually
+        /// calling back is covered by `e2e_tests::reentrant_token_cannot_claim_twice`.
+        fn reenter<F: FnOnce(&mut BondingCurvePresale)>(reentrant_call: F) {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            ink::env::test::set_account_balance::<Env>(ink::env::test::callee::<Env>(), 0);
+            set_now(START);
+            join(&mut presale, accounts.bob, 40_000_000);
+            presale.tokens_owed_to_contributor.insert((1, accounts.bob), &10);
+
+            presale.locked.set(&true);
+            reentrant_call(&mut presale);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Reentrant call")]
+        fn reentrant_claim_tokens_is_rejected() {
+            reenter(|presale| {
+                let mut project = presale.get_project(1).unwrap();
+                project.status = ProjectStatus::Success;
+                presale.save_project(1, project);
+                presale.claim_tokens(1);
+            });
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Reentrant call")]
+        fn reentrant_claim_refund_is_rejected() {
+            reenter(|presale| {
+                let mut project = presale.get_project(1).unwrap();
+                project.status = ProjectStatus::Failed;
+                presale.save_project(1, project);
+                presale.claim_refund(1);
+            });
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Reentrant call")]
+        fn reentrant_join_is_rejected() {
+            reenter(|presale| presale.join_project_presale(1, 0));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Reentrant call")]
+        fn reentrant_leave_is_rejected() {
+            reenter(|presale| presale.leave_ongoing_project_presale(1, 0));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Reentrant call")]
+        fn reentrant_end_presale_is_rejected() {
+            reenter(|presale| {
+                set_now(END + 1);
+                presale.end_presale(1);
+            });
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "No tokens owed to the caller")]
+        fn tokens_cannot_be_claimed_twice() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            presale.tokens_owed_to_contributor.insert((1, accounts.bob), &10);
+            let mut project = presale.get_project(1).unwrap();
+            project.status = ProjectStatus::Success;
+            presale.save_project(1, project);
+
+            set_caller(accounts.bob);
+            // The off-chain environment cannot call the token, so the first claim
+            // stops at the payout, after it has been recorded, and leaves its
+            // lock behind.
+            let first = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                presale.claim_tokens(1)
+            }));
+            assert!(first.is_err());
+            assert_eq!(presale.tokens_owed_to_contributor.get((1, accounts.bob)), None);
+            presale.exit();
+
+            presale.claim_tokens(1);
+        }
+
+        #[ink::test]
+        fn successful_presale_pays_fee_and_creator() {
+            let accounts = accounts();
+            set_caller(accounts.alice);
+            set_now(0);
+            let mut presale = BondingCurvePresale::new(accounts.eve, MAX_SUCCESSFUL_END_FEE);
+            launch(&mut presale, accounts.django, END);
+            ink::env::test::set_account_balance::<Env>(ink::env::test::callee::<Env>(), 0);
+            ink::env::test::set_account_balance::<Env>(accounts.eve, 0);
+            ink::env::test::set_account_balance::<Env>(accounts.django, 0);
+
+            // Three whole units buy the 300 tokens of the soft cap.
+            let funds: Balance = 3 * DECIMALS;
+            set_now(START);
+            join(&mut presale, accounts.bob, funds);
+            assert_eq!(presale.get_project(1).unwrap().raised, SUPPLY * 3 / 10);
+
+            ink::env::test::set_account_balance::<Env>(accounts.charlie, 0);
+            set_caller(accounts.charlie);
+            set_now(END + 1);
+            presale.end_presale(1);
+
+            assert_eq!(presale.get_project(1).unwrap().status, ProjectStatus::Success);
+            assert_eq!(presale.project_funds.get(1), None);
+            // The 10% fee is split between the keeper and the fee collector.
+            let balance = |account| ink::env::test::get_account_balance::<Env>(account);
+            assert_eq!(balance(accounts.charlie), Ok(funds / 100));
+            assert_eq!(balance(accounts.eve), Ok(funds / 100 * 9));
+            assert_eq!(balance(accounts.django), Ok(funds / 10 * 9));
+            assert!(!presale.locked.get().unwrap_or(false));
+        }
+
+        #[ink::test]
+        fn large_raises_are_settled_without_overflow() {
+            let accounts = accounts();
+            set_caller(accounts.alice);
+            set_now(0);
+            let mut presale = BondingCurvePresale::new(accounts.eve, MAX_SUCCESSFUL_END_FEE);
+            presale.set_launcher(Some(accounts.alice));
+            let supply = 2_000_000_000 * DECIMALS;
+            presale.register_launch(accounts.frank, accounts.django, supply, START, END);
+            // Ten million units of an 18 decimal currency, well past the point
+            // where `funds * fee` overflows, buy the billion tokens for sale.
+            let funds: Balance = 10_000_000 * DECIMALS;
+            ink::env::test::set_account_balance::<Env>(ink::env::test::callee::<Env>(), 0);
+            ink::env::test::set_account_balance::<Env>(accounts.eve, 0);
+            ink::env::test::set_account_balance::<Env>(accounts.django, 0);
+            set_now(START);
+            join(&mut presale, accounts.bob, funds);
+            assert_eq!(presale.get_project(1).unwrap().raised, supply / 2);
+
+            ink::env::test::set_account_balance::<Env>(accounts.charlie, 0);
+            set_caller(accounts.charlie);
+            set_now(END);
+            presale.end_presale(1);
+
+            let balance = |account| ink::env::test::get_account_balance::<Env>(account);
+            assert_eq!(balance(accounts.charlie), Ok(funds / 100));
+            assert_eq!(balance(accounts.eve), Ok(funds / 100 * 9));
+            assert_eq!(balance(accounts.django), Ok(funds / 10 * 9));
+        }
+
+        #[ink::test]
+        fn join_buys_tokens_with_the_value_paid() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            ink::env::test::set_account_balance::<Env>(ink::env::test::callee::<Env>(), 0);
+            set_now(START);
+            assert_eq!(presale.calculate_buy_amount(1, DECIMALS), 100 * DECIMALS);
+
+            join(&mut presale, accounts.bob, DECIMALS);
+            join(&mut presale, accounts.charlie, DECIMALS / 2);
+
+            assert_eq!(presale.get_project(1).unwrap().raised, 150 * DECIMALS);
+            assert_eq!(
+                presale.tokens_owed_to_contributor.get((1, accounts.bob)),
+                Some(100 * DECIMALS)
+            );
+            assert_eq!(
+                presale.tokens_owed_to_contributor.get((1, accounts.charlie)),
+                Some(50 * DECIMALS)
+            );
+            assert_eq!(presale.project_funds.get(1), Some(DECIMALS * 3 / 2));
+        }
+
+        #[ink::test]
+        fn join_sends_back_payment_past_the_tokens_for_sale() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            ink::env::test::set_account_balance::<Env>(ink::env::test::callee::<Env>(), 0);
+            set_now(START);
+            join(&mut presale, accounts.bob, 4 * DECIMALS);
+            assert_eq!(presale.calculate_buy_amount(1, 4 * DECIMALS), SUPPLY / 10);
+
+            join(&mut presale, accounts.charlie, 4 * DECIMALS);
+
+            assert_eq!(presale.get_project(1).unwrap().raised, SUPPLY / 2);
+            assert_eq!(presale.contributions.get((1, accounts.charlie)), Some(DECIMALS));
+            assert_eq!(
+                ink::env::test::get_account_balance::<Env>(accounts.charlie),
+                Ok(3 * DECIMALS)
+            );
+            assert_eq!(presale.calculate_buy_amount(1, DECIMALS), 0);
+        }
+
+        #[ink::test]
+        fn mul_div_is_exact_on_large_values() {
+            assert_eq!(
+                BondingCurvePresale::mul_div(u128::MAX, MAX_SUCCESSFUL_END_FEE, DECIMALS),
+                Some(u128::MAX / 10)
+            );
+            assert_eq!(BondingCurvePresale::mul_div(7, 1, 2), Some(3));
+            assert_eq!(BondingCurvePresale::mul_div(u128::MAX, 2, 1), None);
+        }
+
+        #[ink::test]
+        fn finalizable_projects_lists_ended_pending_presales() {
+            let mut presale = presale_with_project();
+            launch(&mut presale, accounts().alice, END + 10);
+            launch(&mut presale, accounts().alice, END);
+            assert_eq!(presale.finalizable_projects(1, 10), (vec![], None));
+
+            set_now(END + 1);
+            assert_eq!(presale.finalizable_projects(1, 10), (vec![1, 3], None));
+            assert_eq!(presale.finalizable_projects(0, 10), (vec![1, 3], None));
+            assert_eq!(presale.finalizable_projects(1, 1), (vec![1], Some(2)));
+            assert_eq!(presale.finalizable_projects(2, 1), (vec![], Some(3)));
+            assert_eq!(presale.finalizable_projects(2, 2), (vec![3], None));
+            assert_eq!(presale.finalizable_projects(4, 10), (vec![], None));
+            assert_eq!(presale.finalizable_projects(u32::MAX, u32::MAX), (vec![], None));
+
+            presale.end_presale(1);
+            assert_eq!(presale.finalizable_projects(1, 10), (vec![3], None));
+        }
+
+        #[ink::test]
+        fn finalizable_projects_scans_a_bounded_range() {
+            let mut presale = presale_with_project();
+            for _ in 0..MAX_SCANNED_PROJECTS + 10 {
+                launch(&mut presale, accounts().alice, END);
+            }
+            set_now(END);
+
+            let (first, next) = presale.finalizable_projects(1, u32::MAX);
+            assert_eq!(first.len(), MAX_SCANNED_PROJECTS as usize);
+            assert_eq!(first.last(), Some(&MAX_SCANNED_PROJECTS));
+            assert_eq!(next, Some(MAX_SCANNED_PROJECTS + 1));
+
+            // Following the cursor visits every project exactly once, whatever
+            // the limit asked for.
+            let mut found = first;
+            let mut cursor = next;
+            while let Some(from_id) = cursor {
+                let (ids, next) = presale.finalizable_projects(from_id, u32::MAX);
+                found.extend(ids);
+                cursor = next;
+            }
+            let expected: Vec<u32> = (1..=presale.last_project_id).collect();
+            assert_eq!(found, expected);
+        }
+
+        #[ink::test]
+        fn claim_refund_settles_ended_presale() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            ink::env::test::set_account_balance::<Env>(ink::env::test::callee::<Env>(), 0);
+            set_now(START);
+            join(&mut presale, accounts.bob, 40_000_000);
+
+            set_now(END + 1);
+            presale.claim_refund(1);
+
+            assert_eq!(presale.get_project(1).unwrap().status, ProjectStatus::Failed);
+            assert_eq!(
+                ink::env::test::get_account_balance::<Env>(accounts.bob),
+                Ok(40_000_000)
+            );
+        }
+
+        const PUBLIC_START: u64 = 1_500;
+
+        fn project_with(status: ProjectStatus) -> Project {
+            Project {
+                token: accounts().frank,
+                initial_token_amount: 1_000,
+                raised: 0,
+                start_time: START,
+                public_start_time: PUBLIC_START,
+                end_time: END,
+                creator: accounts().alice,
+                contributors: Vec::new(),
+                status,
+                price_after_failure: 0,
+                creator_claimed_locked_tokens: false,
+            }
+        }
+
+        #[ink::test]
+        fn status_follows_the_clock() {
+            use PresaleStatus::*;
+            let project = project_with(ProjectStatus::Pending);
+            let cases = [
+                (0, Upcoming),
+                (START - 1, Upcoming),
+                (START, Whitelist),
+                (PUBLIC_START - 1, Whitelist),
+                (PUBLIC_START, Public),
+                (END - 1, Public),
+                (END, Ended),
+                (u64::MAX, Ended),
+            ];
+            for (now, expected) in cases {
+                assert_eq!(
+                    BondingCurvePresale::compute_status(&project, now),
+                    expected,
+                    "at {now}"
+                );
+            }
+        }
+
+        #[ink::test]
+        fn settled_status_ignores_the_clock() {
+            let cases = [
+                (ProjectStatus::Success, PresaleStatus::Succeeded),
+                (ProjectStatus::Failed, PresaleStatus::Failed),
+                (ProjectStatus::Cancelled, PresaleStatus::Cancelled),
+            ];
+            for (stored, expected) in cases {
+                let project = project_with(stored);
+                for now in [0, START, PUBLIC_START, END, u64::MAX] {
+                    assert_eq!(
+                        BondingCurvePresale::compute_status(&project, now),
+                        expected,
+                        "at {now}"
+                    );
+                }
+            }
+        }
+
+        #[ink::test]
+        fn transition_table() {
+            use PresaleStatus::*;
+            let all = [Upcoming, Whitelist, Public, Ended, Succeeded, Failed, Cancelled];
+            let allowed = [
+                (Upcoming, Cancelled),
+                (Upcoming, Failed),
+                (Whitelist, Failed),
+                (Public, Failed),
+                (Ended, Failed),
+                (Ended, Succeeded),
+            ];
+            for from in all {
+                for to in all {
+                    assert_eq!(
+                        from.can_transition_to(to),
+                        allowed.contains(&(from, to)),
+                        "{from:?} -> {to:?}"
+                    );
+                }
+            }
+        }
+
+        #[ink::test]
+        fn presale_can_be_ended_exactly_at_end_time() {
+            let mut presale = presale_with_project();
+            set_now(END);
+            assert_eq!(presale.status_of(1), Some(PresaleStatus::Ended));
+            presale.end_presale(1);
+            assert_eq!(presale.status_of(1), Some(PresaleStatus::Failed));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Presale ended")]
+        fn joining_exactly_at_end_time_fails() {
+            let mut presale = presale_with_project();
+            set_now(END);
+            presale.join_project_presale(1, 0);
+        }
+
+        #[ink::test]
+        fn whitelist_phase_admits_only_whitelisted_accounts() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            presale.configure_whitelist(1, PUBLIC_START, vec![accounts.bob]);
+            assert!(presale.is_whitelisted(1, accounts.bob));
+
+            set_now(START);
+            assert_eq!(presale.status_of(1), Some(PresaleStatus::Whitelist));
+            set_caller(accounts.bob);
+            presale.join_project_presale(1, 0);
+
+            set_caller(accounts.charlie);
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                presale.join_project_presale(1, 0)
+            }));
+            assert!(result.is_err(), "charlie is not whitelisted");
+        }
+
+        #[ink::test]
+        fn creator_can_cancel_upcoming_presale() {
+            let mut presale = presale_with_project();
+            presale.cancel_presale(1);
+            assert_eq!(presale.status_of(1), Some(PresaleStatus::Cancelled));
+            assert_eq!(presale.finalizable_projects(1, 10), (vec![], None));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Caller is not the creator")]
+        fn only_creator_reclaims_tokens() {
+            let mut presale = presale_with_project();
+            presale.cancel_presale(1);
+            set_caller(accounts().bob);
+            presale.reclaim_tokens(1);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Presale was neither cancelled nor failed")]
+        fn running_presale_keeps_its_tokens() {
+            let mut presale = presale_with_project();
+            set_now(START);
+            presale.reclaim_tokens(1);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Invalid status transition")]
+        fn running_presale_cannot_be_cancelled() {
+            let mut presale = presale_with_project();
+            set_now(START);
+            presale.cancel_presale(1);
+        }
+
+        #[ink::test]
+        fn launcher_registers_projects_for_creators() {
+            let mut presale = presale_with_project();
+            let accounts = accounts();
+            presale.set_launcher(Some(accounts.django));
+
+            set_caller(accounts.django);
+            let id = presale.register_launch(accounts.frank, accounts.bob, 1_000, START, END);
+
+            assert_eq!(id, 2);
+            assert_eq!(presale.get_project(id).unwrap().creator, accounts.bob);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Caller is not the launcher")]
+        fn only_launcher_can_register_launches() {
+            let mut presale = presale_with_project();
+            presale.set_launcher(Some(accounts().django));
+            set_caller(accounts().bob);
+            presale.register_launch(accounts().frank, accounts().bob, 1_000, START, END);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Presale has not failed")]
+        fn refunds_require_failed_presale() {
+            let mut presale = presale_with_project();
+            presale.claim_refund(1);
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::ContractsBackend;
+        use reentrant_token::{ReentrantToken, ReentrantTokenRef};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        fn now_millis() -> u64 {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("clock before 1970")
+                .as_millis() as u64
+        }
+
+        #[ink_e2e::test]
+        async fn create_presale_pulls_tokens_from_creator<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let mut constructor = BondingCurvePresaleRef::new(alice, 0);
+            let presale = client
+                .instantiate("bonding_curve_presale", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = presale.call_builder::<BondingCurvePresale>();
+            let mut constructor = Erc20Ref::new(1_000, None, None, None, 18);
+            let token = client
+                .instantiate("erc20", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_calls = token.call_builder::<erc20::Erc20>();
+
+            // when
+            let create =
+                call_builder.create_presale(token.account_id, 1_000, u64::MAX - 1, u64::MAX);
+            let unapproved = client.call(&ink_e2e::alice(), &create).submit().await;
+            let approve = token_calls.approve(presale.account_id, 1_000);
+            client
+                .call(&ink_e2e::alice(), &approve)
+                .submit()
+                .await
+                .expect("approve failed");
+            client
+                .call(&ink_e2e::alice(), &create)
+                .submit()
+                .await
+                .expect("create_presale failed");
+
+            // then
+            assert!(unapproved.is_err(), "presale created without its tokens");
+            let deposited = client
+                .call(&ink_e2e::alice(), &token_calls.balance_of(presale.account_id))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(deposited, 1_000);
+            let left = client
+                .call(&ink_e2e::alice(), &token_calls.balance_of(alice))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(left, 0);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn reentrant_token_cannot_claim_twice<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let mut constructor = BondingCurvePresaleRef::new(alice, 0);
+            let presale = client
+                .instantiate("bonding_curve_presale", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = presale.call_builder::<BondingCurvePresale>();
+            let mut constructor = ReentrantTokenRef::new(2_000_000_000_000);
+            let token = client
+                .instantiate("reentrant_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let mut token_calls = token.call_builder::<ReentrantToken>();
+
+            // The token joins project 1 itself, so it is owed a refund once the
+            // owner fails the project. The project is large enough for the whole
+            // payment to buy tokens.
+            let start = now_millis() + 10_000;
+            let supply = 1_000_000_000_000;
+            let create =
+                call_builder.create_presale(token.account_id, supply, start, start + 3_600_000);
+            client
+                .call(&ink_e2e::alice(), &create)
+                .submit()
+                .await
+                .expect("create_presale failed");
+            std::thread::sleep(std::time::Duration::from_millis(
+                start.saturating_sub(now_millis()) + 1_000,
+            ));
+            let join = token_calls.join(presale.account_id, 1);
+            client
+                .call(&ink_e2e::alice(), &join)
+                .value(1_000_000_000)
+                .submit()
+                .await
+                .expect("join failed");
+            client
+                .call(&ink_e2e::alice(), &call_builder.emergency_fail(1))
+                .submit()
+                .await
+                .expect("emergency_fail failed");
+
+            // when
+            // Every token move now calls `claim_tokens(1)` and `claim_refund(1)`
+            // from inside the presale call that moves the tokens.
+            client
+                .call(&ink_e2e::alice(), &token_calls.attack(presale.account_id, 1))
+                .submit()
+                .await
+                .expect("attack failed");
+            let create =
+                call_builder.create_presale(token.account_id, 1_000, u64::MAX - 1, u64::MAX);
+            client
+                .call(&ink_e2e::alice(), &create)
+                .submit()
+                .await
+                .expect("create_presale failed");
+
+            // then
+            let callbacks = client
+                .call(&ink_e2e::alice(), &token_calls.callbacks())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(callbacks, 2, "token did not call back");
+            let reentered = client
+                .call(&ink_e2e::alice(), &token_calls.reentered())
+                .dry_run()
+                .await?
+                .return_value();
+            assert!(!reentered, "presale accepted a re-entrant claim");
+
+            let claim = token_calls.claim_refund(presale.account_id, 1);
+            let first = client
+                .call(&ink_e2e::alice(), &claim)
+                .submit()
+                .await
+                .expect("claim_refund failed")
+                .return_value();
+            let second = client
+                .call(&ink_e2e::alice(), &claim)
+                .submit()
+                .await
+                .expect("claim_refund failed")
+                .return_value();
+            assert!(first, "refund was not paid");
+            assert!(!second, "refund was paid twice");
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn projects_survive_upgrade<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let mut constructor = BondingCurvePresaleRef::new(alice, 0);
+            let presale = client
+                .instantiate("bonding_curve_presale", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = presale.call_builder::<BondingCurvePresale>();
+
+            let mut constructor = Erc20Ref::new(1_000, None, None, None, 18);
+            let token = client
+                .instantiate("erc20", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("token instantiate failed");
+            let approve = token
+                .call_builder::<erc20::Erc20>()
+                .approve(presale.account_id, 1_000);
+            client
+                .call(&ink_e2e::alice(), &approve)
+                .submit()
+                .await
+                .expect("approve failed");
+            let create =
+                call_builder.create_presale(token.account_id, 1_000, u64::MAX - 1, u64::MAX);
+            client
+                .call(&ink_e2e::alice(), &create)
+                .submit()
+                .await
+                .expect("create_presale failed");
+
+            let get_project = call_builder.get_project(1);
+            let before = client
+                .call(&ink_e2e::alice(), &get_project)
+                .dry_run()
+                .await?
+                .return_value();
+            assert!(before.is_some(), "project must exist before the upgrade");
+
+            // when
+            // The next release expects `STORAGE_VERSION + 1`.
+            let code_hash = client
+                .upload("bonding_curve_presale_next", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+            let code_hash: [u8; 32] = code_hash.as_ref().try_into().unwrap();
+            let upgrade = call_builder.upgrade(Hash::from(code_hash));
+            client
+                .call(&ink_e2e::alice(), &upgrade)
+                .submit()
+                .await
+                .expect("upgrade failed")
+                .return_value()
+                .expect("upgrade returned an error");
+            let version = client
+                .call(&ink_e2e::alice(), &call_builder.storage_version())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(version, STORAGE_VERSION, "storage migrated before `migrate`");
+            client
+                .call(&ink_e2e::alice(), &call_builder.migrate())
+                .submit()
+                .await
+                .expect("migrate failed")
+                .return_value()
+                .expect("migrate returned an error");
+
+            // then
+            let version = client
+                .call(&ink_e2e::alice(), &call_builder.storage_version())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(version, STORAGE_VERSION + 1);
+            let again = client
+                .call(&ink_e2e::alice(), &call_builder.migrate())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(again, Err(Error::StorageUpToDate));
+            let after = client
+                .call(&ink_e2e::alice(), &get_project)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(before, after, "project changed across the upgrade");
+
+            Ok(())
+        }
     }
 }
@@ -3,16 +3,129 @@
 pub use self::erc20::{
     Erc20,
     Erc20Ref,
+    Error,
+    Features,
+    PermitSignature,
+    Role,
 };
 
+use ink::prelude::{
+    string::String,
+    vec::Vec,
+};
+use ink::primitives::AccountId;
+
+/// The PSP22 error types.
+#[derive(Debug, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum PSP22Error {
+    /// Custom error type for implementation-based errors.
+    Custom(String),
+    /// Returned when an account does not have enough tokens to complete the operation.
+    InsufficientBalance,
+    /// Returned if there is not enough allowance to complete the operation.
+    InsufficientAllowance,
+    /// Returned if recipient's address is zero.
+    ZeroRecipientAddress,
+    /// Returned if sender's address is zero.
+    ZeroSenderAddress,
+    /// Returned if a safe transfer check failed.
+    SafeTransferCheckFailed(String),
+}
+
+/// The PSP22 fungible token standard.
+///
+/// The selectors are spelled out so they stay those of the standard, which
+/// wallets and DEXes call, whatever this trait is renamed to.
+#[ink::trait_definition]
+pub trait PSP22 {
+    /// Returns the total token supply.
+    #[ink(message, selector = 0x162df8c2)]
+    fn total_supply(&self) -> u128;
+
+    /// Returns the account balance for the specified `owner`.
+    #[ink(message, selector = 0x6568382f)]
+    fn balance_of(&self, owner: AccountId) -> u128;
+
+    /// Returns the amount which `spender` is still allowed to withdraw from `owner`.
+    #[ink(message, selector = 0x4d47d921)]
+    fn allowance(&self, owner: AccountId, spender: AccountId) -> u128;
+
+    /// Transfers `value` tokens from the caller to `to`. `data` is not
+    /// interpreted by the token.
+    #[ink(message, selector = 0xdb20f9f5)]
+    fn transfer(
+        &mut self,
+        to: AccountId,
+        value: u128,
+        data: Vec<u8>,
+    ) -> Result<(), PSP22Error>;
+
+    /// Transfers `value` tokens on the behalf of `from` to `to`, spending the
+    /// caller's allowance. `data` is not interpreted by the token.
+    #[ink(message, selector = 0x54b3c76e)]
+    fn transfer_from(
+        &mut self,
+        from: AccountId,
+        to: AccountId,
+        value: u128,
+        data: Vec<u8>,
+    ) -> Result<(), PSP22Error>;
+
+    /// Sets the allowance of `spender` over the caller's tokens to `value`.
+    #[ink(message, selector = 0xb20f1bbd)]
+    fn approve(&mut self, spender: AccountId, value: u128) -> Result<(), PSP22Error>;
+
+    /// Raises the allowance of `spender` over the caller's tokens by
+    /// `delta_value`.
+    #[ink(message, selector = 0x96d6b57a)]
+    fn increase_allowance(
+        &mut self,
+        spender: AccountId,
+        delta_value: u128,
+    ) -> Result<(), PSP22Error>;
+
+    /// Lowers the allowance of `spender` over the caller's tokens by
+    /// `delta_value`.
+    #[ink(message, selector = 0xfecb57d5)]
+    fn decrease_allowance(
+        &mut self,
+        spender: AccountId,
+        delta_value: u128,
+    ) -> Result<(), PSP22Error>;
+}
 
 #[ink::contract]
 mod erc20 {
+    use crate::{
+        PSP22Error,
+        PSP22,
+    };
+    use ink::prelude::{
+        string::String,
+        vec::Vec,
+    };
+    use ink::env::hash::Blake2x256;
     use ink::storage::Mapping;
 
+    /// Prefix of every `permit` payload, so its signatures cannot be replayed
+    /// as signatures over anything else.
+    const PERMIT_DOMAIN: &[u8] = b"erc20::permit";
+
+    /// A balance together with the snapshot id it was recorded for.
+    type Checkpoint = (u32, Balance);
+
+    /// A delegate's votes together with the block they apply from.
+    type VoteCheckpoint = (BlockNumber, Balance);
+
+    /// The features of tokens deployed through `new` and `new_capped`.
+    const ALL_FEATURES: Features = Features {
+        mintable: true,
+        burnable: true,
+    };
+
     /// A simple ERC-20 contract.
     #[ink(storage)]
-    #[derive(Default)]
     pub struct Erc20 {
         /// Total token supply.
         total_supply: Balance,
@@ -21,6 +134,76 @@ mod erc20 {
         /// Mapping of the token amount which an account is allowed to withdraw
         /// from another account.
         allowances: Mapping<(AccountId, AccountId), Balance>,
+        /// Token name.
+        name: Option<String>,
+        /// Token symbol.
+        symbol: Option<String>,
+        /// Number of decimals balances are denominated in.
+        decimals: u8,
+        /// URI of the token logo.
+        logo_uri: Option<String>,
+        /// Accounts holding each role.
+        roles: Mapping<(Role, AccountId), ()>,
+        /// Whether transfers and approvals are suspended.
+        paused: bool,
+        /// Most tokens that can ever be in circulation, if limited.
+        cap: Option<Balance>,
+        /// Whether the supply can grow or shrink after construction.
+        features: Features,
+        /// Number of permits each owner has used so far.
+        nonces: Mapping<AccountId, u64>,
+        /// Id of the latest snapshot, `0` before the first one.
+        snapshot_id: u32,
+        /// Number of balance checkpoints of each account.
+        balance_checkpoint_count: Mapping<AccountId, u32>,
+        /// Each account's balance as of a snapshot id, by ascending id. A
+        /// checkpoint is written by the first balance change after a snapshot.
+        balance_checkpoints: Mapping<(AccountId, u32), Checkpoint>,
+        /// Number of total supply checkpoints.
+        total_supply_checkpoint_count: u32,
+        /// The total supply as of a snapshot id, by ascending id.
+        total_supply_checkpoints: Mapping<u32, Checkpoint>,
+        /// Account each holder lends their voting power to.
+        delegates: Mapping<AccountId, AccountId>,
+        /// Number of vote checkpoints of each delegate.
+        vote_checkpoint_count: Mapping<AccountId, u32>,
+        /// Each delegate's votes from a block on, by ascending block number.
+        vote_checkpoints: Mapping<(AccountId, u32), VoteCheckpoint>,
+    }
+
+    /// Signature of the owner over a `permit_message`.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum PermitSignature {
+        /// Made with the sr25519 key the owner account is. Only accepted by
+        /// builds with the `sr25519` feature.
+        Sr25519([u8; 64]),
+        /// Made with the ECDSA key whose blake2 hash the owner account is.
+        Ecdsa([u8; 65]),
+    }
+
+    /// Which supply changes a token allows after construction.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct Features {
+        /// Minters can create new tokens.
+        pub mintable: bool,
+        /// Holders can destroy their tokens.
+        pub burnable: bool,
+    }
+
+    /// What an account is allowed to do beyond holding tokens.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Role {
+        /// Grants and revokes roles and updates the metadata.
+        Admin,
+        /// Mints new tokens.
+        Minter,
+        /// Pauses and unpauses transfers.
+        Pauser,
     }
 
     /// Event emitted when a token transfer occurs.
@@ -44,6 +227,77 @@ mod erc20 {
         value: Balance,
     }
 
+    /// Event emitted when the admin updates the token metadata.
+    #[ink(event)]
+    pub struct MetadataUpdated {
+        name: Option<String>,
+        symbol: Option<String>,
+        logo_uri: Option<String>,
+    }
+
+    /// Event emitted when `account` is granted `role` by `sender`.
+    #[ink(event)]
+    pub struct RoleGranted {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        sender: AccountId,
+    }
+
+    /// Event emitted when `account` loses `role`, either revoked by an admin
+    /// or renounced, in which case `sender` is `account`.
+    #[ink(event)]
+    pub struct RoleRevoked {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        sender: AccountId,
+    }
+
+    /// Event emitted when `delegator` moves their voting power to `to_delegate`.
+    #[ink(event)]
+    pub struct DelegateChanged {
+        #[ink(topic)]
+        delegator: AccountId,
+        #[ink(topic)]
+        from_delegate: Option<AccountId>,
+        #[ink(topic)]
+        to_delegate: AccountId,
+    }
+
+    /// Event emitted when the votes of `delegate` change.
+    #[ink(event)]
+    pub struct DelegateVotesChanged {
+        #[ink(topic)]
+        delegate: AccountId,
+        previous_votes: Balance,
+        new_votes: Balance,
+    }
+
+    /// Event emitted when an admin takes a snapshot.
+    #[ink(event)]
+    pub struct Snapshot {
+        id: u32,
+    }
+
+    /// Event emitted when a pauser suspends transfers and approvals.
+    #[ink(event)]
+    pub struct Paused {
+        #[ink(topic)]
+        account: AccountId,
+    }
+
+    /// Event emitted when a pauser resumes transfers and approvals.
+    #[ink(event)]
+    pub struct Unpaused {
+        #[ink(topic)]
+        account: AccountId,
+    }
+
     /// The ERC-20 error types.
     #[derive(Debug, PartialEq, Eq)]
     #[ink::scale_derive(Encode, Decode, TypeInfo)]
@@ -52,15 +306,135 @@ mod erc20 {
         InsufficientBalance,
         /// Returned if not enough allowance to fulfill a request is available.
         InsufficientAllowance,
+        /// Returned if the caller lacks the role the message requires.
+        MissingRole,
+        /// Returned if transfers and approvals are paused.
+        Paused,
+        /// Returned if `unpause` is called while not paused.
+        NotPaused,
+        /// Returned if minting would push the total supply above the cap.
+        CapExceeded,
+        /// Returned if tokens are minted on a token deployed without minting.
+        NotMintable,
+        /// Returned if tokens are burnt on a token deployed without burning.
+        NotBurnable,
+        /// Returned if a permit is used after its deadline.
+        PermitExpired,
+        /// Returned if a permit is not signed by its owner.
+        InvalidSignature,
+        /// Returned if a permit is signed with a scheme this build does not
+        /// verify.
+        UnsupportedSignature,
+        /// Returned if no snapshot with the requested id has been taken.
+        InvalidSnapshotId,
+        /// Returned if past votes are requested for a block that is not
+        /// finished yet.
+        FutureLookup,
+        /// Returned if an allowance or the total supply would exceed
+        /// `Balance::MAX`.
+        Overflow,
     }
 
     /// The ERC-20 result type.
     pub type Result<T> = core::result::Result<T, Error>;
 
+    impl From<Error> for PSP22Error {
+        fn from(error: Error) -> Self {
+            match error {
+                Error::InsufficientBalance => PSP22Error::InsufficientBalance,
+                Error::InsufficientAllowance => PSP22Error::InsufficientAllowance,
+                Error::MissingRole => PSP22Error::Custom(String::from("MissingRole")),
+                Error::Paused => PSP22Error::Custom(String::from("Paused")),
+                Error::NotPaused => PSP22Error::Custom(String::from("NotPaused")),
+                Error::CapExceeded => PSP22Error::Custom(String::from("CapExceeded")),
+                Error::NotMintable => PSP22Error::Custom(String::from("NotMintable")),
+                Error::NotBurnable => PSP22Error::Custom(String::from("NotBurnable")),
+                Error::PermitExpired => PSP22Error::Custom(String::from("PermitExpired")),
+                Error::InvalidSignature => {
+                    PSP22Error::Custom(String::from("InvalidSignature"))
+                }
+                Error::UnsupportedSignature => {
+                    PSP22Error::Custom(String::from("UnsupportedSignature"))
+                }
+                Error::InvalidSnapshotId => {
+                    PSP22Error::Custom(String::from("InvalidSnapshotId"))
+                }
+                Error::FutureLookup => PSP22Error::Custom(String::from("FutureLookup")),
+                Error::Overflow => PSP22Error::Custom(String::from("Overflow")),
+            }
+        }
+    }
+
     impl Erc20 {
-        /// Creates a new ERC-20 contract with the specified initial supply.
+        /// Creates a new ERC-20 contract with the specified initial supply and
+        /// metadata that can be minted and burnt. The caller starts with every
+        /// role.
+        #[ink(constructor)]
+        pub fn new(
+            total_supply: Balance,
+            name: Option<String>,
+            symbol: Option<String>,
+            logo_uri: Option<String>,
+            decimals: u8,
+        ) -> Self {
+            Self::new_with_features(
+                total_supply,
+                None,
+                ALL_FEATURES,
+                name,
+                symbol,
+                logo_uri,
+                decimals,
+            )
+        }
+
+        /// Creates a new ERC-20 contract like `new` whose total supply can
+        /// never exceed `cap`.
+        ///
+        /// # Panics
+        ///
+        /// If `total_supply` exceeds `cap`.
+        #[ink(constructor)]
+        pub fn new_capped(
+            total_supply: Balance,
+            cap: Balance,
+            name: Option<String>,
+            symbol: Option<String>,
+            logo_uri: Option<String>,
+            decimals: u8,
+        ) -> Self {
+            Self::new_with_features(
+                total_supply,
+                Some(cap),
+                ALL_FEATURES,
+                name,
+                symbol,
+                logo_uri,
+                decimals,
+            )
+        }
+
+        /// Creates a new ERC-20 contract like `new`, capped at `cap` if given,
+        /// that only allows the supply changes in `features`. The caller gets
+        /// no `Minter` role if the token is not mintable.
+        ///
+        /// # Panics
+        ///
+        /// If `total_supply` exceeds `cap`.
         #[ink(constructor)]
-        pub fn new(total_supply: Balance) -> Self {
+        pub fn new_with_features(
+            total_supply: Balance,
+            cap: Option<Balance>,
+            features: Features,
+            name: Option<String>,
+            symbol: Option<String>,
+            logo_uri: Option<String>,
+            decimals: u8,
+        ) -> Self {
+            assert!(
+                cap.is_none_or(|cap| total_supply <= cap),
+                "Initial supply exceeds the cap"
+            );
             let mut balances = Mapping::default();
             let caller = Self::env().caller();
             balances.insert(caller, &total_supply);
@@ -69,11 +443,203 @@ mod erc20 {
                 to: Some(caller),
                 value: total_supply,
             });
+            let mut roles = Mapping::default();
+            for role in [Role::Admin, Role::Minter, Role::Pauser] {
+                if role != Role::Minter || features.mintable {
+                    roles.insert((role, caller), &());
+                }
+            }
             Self {
                 total_supply,
                 balances,
                 allowances: Default::default(),
+                name,
+                symbol,
+                decimals,
+                logo_uri,
+                roles,
+                paused: false,
+                cap,
+                features,
+                nonces: Mapping::default(),
+                snapshot_id: 0,
+                balance_checkpoint_count: Mapping::default(),
+                balance_checkpoints: Mapping::default(),
+                total_supply_checkpoint_count: 0,
+                total_supply_checkpoints: Mapping::default(),
+                delegates: Mapping::default(),
+                vote_checkpoint_count: Mapping::default(),
+                vote_checkpoints: Mapping::default(),
+            }
+        }
+
+        /// Returns the token name.
+        #[ink(message, selector = 0x3d261bd4)]
+        pub fn token_name(&self) -> Option<String> {
+            self.name.clone()
+        }
+
+        /// Returns the token symbol.
+        #[ink(message, selector = 0x34205be5)]
+        pub fn token_symbol(&self) -> Option<String> {
+            self.symbol.clone()
+        }
+
+        /// Returns the number of decimals balances are denominated in.
+        #[ink(message, selector = 0x7271b782)]
+        pub fn token_decimals(&self) -> u8 {
+            self.decimals
+        }
+
+        /// Returns the URI of the token logo.
+        #[ink(message)]
+        pub fn logo_uri(&self) -> Option<String> {
+            self.logo_uri.clone()
+        }
+
+        /// Replaces the name, symbol and logo URI of the token. The decimals
+        /// are fixed at construction since balances depend on them.
+        ///
+        /// A `MetadataUpdated` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `MissingRole` error if the caller is not an admin.
+        #[ink(message)]
+        pub fn set_metadata(
+            &mut self,
+            name: Option<String>,
+            symbol: Option<String>,
+            logo_uri: Option<String>,
+        ) -> Result<()> {
+            self.ensure_role(Role::Admin)?;
+            self.name.clone_from(&name);
+            self.symbol.clone_from(&symbol);
+            self.logo_uri.clone_from(&logo_uri);
+            self.env().emit_event(MetadataUpdated {
+                name,
+                symbol,
+                logo_uri,
+            });
+            Ok(())
+        }
+
+        /// Returns whether `account` holds `role`.
+        #[ink(message)]
+        pub fn has_role(&self, role: Role, account: AccountId) -> bool {
+            self.roles.contains((role, account))
+        }
+
+        /// Gives `role` to `account`.
+        ///
+        /// A `RoleGranted` event is emitted unless `account` already held it.
+        ///
+        /// # Errors
+        ///
+        /// Returns `MissingRole` error if the caller is not an admin.
+        ///
+        /// Returns `NotMintable` error if `role` is `Minter` and the token was
+        /// deployed without minting.
+        #[ink(message)]
+        pub fn grant_role(&mut self, role: Role, account: AccountId) -> Result<()> {
+            self.ensure_role(Role::Admin)?;
+            if role == Role::Minter && !self.features.mintable {
+                return Err(Error::NotMintable)
+            }
+            if self.roles.insert((role, account), &()).is_none() {
+                self.env().emit_event(RoleGranted {
+                    role,
+                    account,
+                    sender: self.env().caller(),
+                });
+            }
+            Ok(())
+        }
+
+        /// Takes `role` away from `account`.
+        ///
+        /// A `RoleRevoked` event is emitted if `account` held it.
+        ///
+        /// # Errors
+        ///
+        /// Returns `MissingRole` error if the caller is not an admin.
+        #[ink(message)]
+        pub fn revoke_role(&mut self, role: Role, account: AccountId) -> Result<()> {
+            self.ensure_role(Role::Admin)?;
+            self.remove_role(role, account);
+            Ok(())
+        }
+
+        /// Gives up `role` held by the caller. An admin renouncing `Admin`
+        /// cannot get it back unless another admin grants it.
+        ///
+        /// A `RoleRevoked` event is emitted if the caller held it.
+        #[ink(message)]
+        pub fn renounce_role(&mut self, role: Role) -> Result<()> {
+            let caller = self.env().caller();
+            self.remove_role(role, caller);
+            Ok(())
+        }
+
+        /// Returns the most tokens that can ever be in circulation, or `None`
+        /// if the supply is not capped.
+        #[ink(message)]
+        pub fn cap(&self) -> Option<Balance> {
+            self.cap
+        }
+
+        /// Returns which supply changes the token allows.
+        #[ink(message)]
+        pub fn features(&self) -> Features {
+            self.features
+        }
+
+        /// Returns whether transfers and approvals are paused.
+        #[ink(message)]
+        pub fn paused(&self) -> bool {
+            self.paused
+        }
+
+        /// Suspends transfers and approvals until `unpause` is called.
+        ///
+        /// A `Paused` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `MissingRole` error if the caller is not a pauser.
+        ///
+        /// Returns `Paused` error if the token is already paused.
+        #[ink(message)]
+        pub fn pause(&mut self) -> Result<()> {
+            self.ensure_role(Role::Pauser)?;
+            self.ensure_not_paused()?;
+            self.paused = true;
+            self.env().emit_event(Paused {
+                account: self.env().caller(),
+            });
+            Ok(())
+        }
+
+        /// Resumes transfers and approvals.
+        ///
+        /// An `Unpaused` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `MissingRole` error if the caller is not a pauser.
+        ///
+        /// Returns `NotPaused` error if the token is not paused.
+        #[ink(message)]
+        pub fn unpause(&mut self) -> Result<()> {
+            self.ensure_role(Role::Pauser)?;
+            if !self.paused {
+                return Err(Error::NotPaused)
             }
+            self.paused = false;
+            self.env().emit_event(Unpaused {
+                account: self.env().caller(),
+            });
+            Ok(())
         }
 
         /// Returns the total token supply.
@@ -145,346 +711,1802 @@ mod erc20 {
         /// `value`.
         ///
         /// An `Approval` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `Paused` error if the token is paused.
         #[ink(message)]
         pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<()> {
             let owner = self.env().caller();
-            self.allowances.insert((&owner, &spender), &value);
-            self.env().emit_event(Approval {
-                owner,
-                spender,
-                value,
-            });
-            Ok(())
+            self.approve_impl(&owner, &spender, value)
         }
 
-        /// Transfers `value` tokens on the behalf of `from` to the account `to`.
+        /// Atomically raises the allowance of `spender` over the caller's tokens
+        /// by `delta`, avoiding the race of overwriting it with `approve`.
         ///
-        /// This can be used to allow a contract to transfer tokens on ones behalf and/or
-        /// to charge fees in sub-currencies, for example.
+        /// An `Approval` event with the new allowance is emitted.
         ///
-        /// On success a `Transfer` event is emitted.
+        /// # Errors
+        ///
+        /// Returns `Overflow` error if the allowance would exceed `Balance::MAX`.
+        #[ink(message)]
+        pub fn increase_allowance(&mut self, spender: AccountId, delta: Balance) -> Result<()> {
+            let owner = self.env().caller();
+            self.increase_allowance_impl(&owner, &spender, delta)
+        }
+
+        /// Atomically lowers the allowance of `spender` over the caller's tokens
+        /// by `delta`.
+        ///
+        /// An `Approval` event with the new allowance is emitted.
         ///
         /// # Errors
         ///
-        /// Returns `InsufficientAllowance` error if there are not enough tokens allowed
-        /// for the caller to withdraw from `from`.
+        /// Returns `InsufficientAllowance` error if the allowance is less than
+        /// `delta`.
+        #[ink(message)]
+        pub fn decrease_allowance(&mut self, spender: AccountId, delta: Balance) -> Result<()> {
+            let owner = self.env().caller();
+            self.decrease_allowance_impl(&owner, &spender, delta)
+        }
+
+        /// Returns the number of permits `owner` has used, which the next
+        /// permit of `owner` is bound to.
+        #[ink(message)]
+        pub fn nonces(&self, owner: AccountId) -> u64 {
+            self.nonces.get(owner).unwrap_or_default()
+        }
+
+        /// Returns the message `owner` signs to let `spender` withdraw up to
+        /// `value` of their tokens until `deadline` through `permit`.
         ///
-        /// Returns `InsufficientBalance` error if there are not enough tokens on
-        /// the account balance of `from`.
+        /// It is the blake2 hash of this contract's address, the arguments and
+        /// `owner`'s current nonce behind `PERMIT_DOMAIN`.
         #[ink(message)]
-        pub fn transfer_from(
-            &mut self,
-            from: AccountId,
-            to: AccountId,
+        pub fn permit_message(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
             value: Balance,
-        ) -> Result<()> {
-            let caller = self.env().caller();
-            let allowance = self.allowance_impl(&from, &caller);
-            if allowance < value {
-                return Err(Error::InsufficientAllowance)
-            }
-            self.transfer_from_to(&from, &to, value)?;
-            // We checked that allowance >= value
-            #[allow(clippy::arithmetic_side_effects)]
-            self.allowances
-                .insert((&from, &caller), &(allowance - value));
-            Ok(())
+            deadline: Timestamp,
+        ) -> [u8; 32] {
+            let mut message = [0u8; 32];
+            ink::env::hash_encoded::<Blake2x256, _>(
+                &(
+                    PERMIT_DOMAIN,
+                    self.env().account_id(),
+                    owner,
+                    spender,
+                    value,
+                    self.nonces(owner),
+                    deadline,
+                ),
+                &mut message,
+            );
+            message
         }
 
-        /// Transfers `value` amount of tokens from the caller's account to account `to`.
+        /// Sets the allowance of `spender` over `owner`'s tokens to `value` on
+        /// behalf of `owner`, who signed the `permit_message` off-chain.
+        /// Anyone can submit the permit, e.g. bundled with the action that
+        /// spends it. Each signature works once.
         ///
-        /// On success a `Transfer` event is emitted.
+        /// An `Approval` event is emitted.
         ///
         /// # Errors
         ///
-        /// Returns `InsufficientBalance` error if there are not enough tokens on
-        /// the caller's account balance.
-        fn transfer_from_to(
+        /// Returns `PermitExpired` error if the block timestamp is past
+        /// `deadline`.
+        ///
+        /// Returns `UnsupportedSignature` error if `signature` is an sr25519
+        /// signature and the contract was built without the `sr25519` feature.
+        ///
+        /// Returns `InvalidSignature` error if `signature` is not `owner`'s
+        /// signature over the current `permit_message`.
+        ///
+        /// Returns `Paused` error if the token is paused.
+        #[ink(message)]
+        pub fn permit(
             &mut self,
-            from: &AccountId,
-            to: &AccountId,
+            owner: AccountId,
+            spender: AccountId,
             value: Balance,
+            deadline: Timestamp,
+            signature: PermitSignature,
         ) -> Result<()> {
-            let from_balance = self.balance_of_impl(from);
-            if from_balance < value {
-                return Err(Error::InsufficientBalance)
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::PermitExpired)
             }
-            // We checked that from_balance >= value
+            let message = self.permit_message(owner, spender, value, deadline);
+            let signed_by_owner = match signature {
+                #[cfg(feature = "sr25519")]
+                PermitSignature::Sr25519(signature) => {
+                    ink::env::sr25519_verify(&signature, &message, owner.as_ref()).is_ok()
+                }
+                #[cfg(not(feature = "sr25519"))]
+                PermitSignature::Sr25519(_) => return Err(Error::UnsupportedSignature),
+                PermitSignature::Ecdsa(signature) => {
+                    let mut public_key = [0u8; 33];
+                    match ink::env::ecdsa_recover(&signature, &message, &mut public_key) {
+                        Ok(()) => {
+                            let mut signer = [0u8; 32];
+                            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut signer);
+                            AccountId::from(signer) == owner
+                        }
+                        Err(_) => false,
+                    }
+                }
+            };
+            if !signed_by_owner {
+                return Err(Error::InvalidSignature)
+            }
+            // A nonce cannot realistically reach `u64::MAX`.
             #[allow(clippy::arithmetic_side_effects)]
-            self.balances.insert(from, &(from_balance - value));
-            let to_balance = self.balance_of_impl(to);
-            self.balances
-                .insert(to, &(to_balance.checked_add(value).unwrap()));
-            self.env().emit_event(Transfer {
-                from: Some(*from),
-                to: Some(*to),
-                value,
-            });
-            Ok(())
+            self.nonces.insert(owner, &(self.nonces(owner) + 1));
+            self.approve_impl(&owner, &spender, value)
         }
-    }
-
-    #[cfg(test)]
-    mod tests {
-        use super::*;
 
-        use ink::primitives::{
-            Clear,
-            Hash,
-        };
+        /// Records the current balances and total supply under a new snapshot
+        /// id and returns it. Ids start at `1`.
+        ///
+        /// A `Snapshot` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `MissingRole` error if the caller is not an admin.
+        #[ink(message)]
+        pub fn snapshot(&mut self) -> Result<u32> {
+            self.ensure_role(Role::Admin)?;
+            let id = self.snapshot_id.checked_add(1).ok_or(Error::Overflow)?;
+            self.snapshot_id = id;
+            self.env().emit_event(Snapshot { id });
+            Ok(id)
+        }
 
-        fn assert_transfer_event(
-            event: &ink::env::test::EmittedEvent,
-            expected_from: Option<AccountId>,
-            expected_to: Option<AccountId>,
-            expected_value: Balance,
-        ) {
-            let decoded_event =
-                <Transfer as ink::scale::Decode>::decode(&mut &event.data[..])
-                    .expect("encountered invalid contract event data buffer");
-            let Transfer { from, to, value } = decoded_event;
-            assert_eq!(from, expected_from, "encountered invalid Transfer.from");
-            assert_eq!(to, expected_to, "encountered invalid Transfer.to");
-            assert_eq!(value, expected_value, "encountered invalid Trasfer.value");
+        /// Returns the id of the latest snapshot, `0` before the first one.
+        #[ink(message)]
+        pub fn current_snapshot_id(&self) -> u32 {
+            self.snapshot_id
+        }
+
+        /// Returns the balance of `account` when snapshot `snapshot_id` was
+        /// taken.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InvalidSnapshotId` error if that snapshot was not taken.
+        #[ink(message)]
+        pub fn balance_of_at(&self, account: AccountId, snapshot_id: u32) -> Result<Balance> {
+            self.ensure_snapshot_exists(snapshot_id)?;
+            let count = self.balance_checkpoint_count.get(account).unwrap_or_default();
+            Ok(Self::checkpoint_at(count, snapshot_id, |index| {
+                self.balance_checkpoints.get((account, index))
+            })
+            .unwrap_or_else(|| self.balance_of_impl(&account)))
+        }
+
+        /// Returns the total supply when snapshot `snapshot_id` was taken.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InvalidSnapshotId` error if that snapshot was not taken.
+        #[ink(message)]
+        pub fn total_supply_at(&self, snapshot_id: u32) -> Result<Balance> {
+            self.ensure_snapshot_exists(snapshot_id)?;
+            Ok(Self::checkpoint_at(
+                self.total_supply_checkpoint_count,
+                snapshot_id,
+                |index| self.total_supply_checkpoints.get(index),
+            )
+            .unwrap_or(self.total_supply))
+        }
+
+        /// Returns the account `account` lends its voting power to, if any.
+        #[ink(message)]
+        pub fn delegates(&self, account: AccountId) -> Option<AccountId> {
+            self.delegates.get(account)
+        }
+
+        /// Lends the caller's voting power, now and after every future balance
+        /// change, to `to`. Holders must delegate, possibly to themselves,
+        /// for their tokens to count as votes.
+        ///
+        /// A `DelegateChanged` event is emitted, plus a `DelegateVotesChanged`
+        /// event for each delegate whose votes change.
+        #[ink(message)]
+        pub fn delegate(&mut self, to: AccountId) -> Result<()> {
+            let delegator = self.env().caller();
+            let from_delegate = self.delegates.get(delegator);
+            self.delegates.insert(delegator, &to);
+            self.env().emit_event(DelegateChanged {
+                delegator,
+                from_delegate,
+                to_delegate: to,
+            });
+            let balance = self.balance_of_impl(&delegator);
+            self.move_votes(from_delegate, Some(to), balance);
+            Ok(())
+        }
+
+        /// Returns the votes currently delegated to `account`.
+        #[ink(message)]
+        pub fn get_votes(&self, account: AccountId) -> Balance {
+            let count = self.vote_checkpoint_count.get(account).unwrap_or_default();
+            count
+                .checked_sub(1)
+                .and_then(|index| self.vote_checkpoints.get((account, index)))
+                .map_or(0, |(_, votes)| votes)
+        }
+
+        /// Returns the votes delegated to `account` at the end of `block`.
+        ///
+        /// # Errors
+        ///
+        /// Returns `FutureLookup` error if `block` is not before the current
+        /// block, whose votes can still change.
+        #[ink(message)]
+        pub fn get_past_votes(&self, account: AccountId, block: BlockNumber) -> Result<Balance> {
+            if block >= self.env().block_number() {
+                return Err(Error::FutureLookup)
+            }
+            // Find the first checkpoint written after `block`; the one before it
+            // holds the votes at `block`.
+            let count = self.vote_checkpoint_count.get(account).unwrap_or_default();
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                // `low < high`, so neither can overflow.
+                #[allow(clippy::arithmetic_side_effects)]
+                let mid = low + (high - low) / 2;
+                let after = self
+                    .vote_checkpoints
+                    .get((account, mid))
+                    .is_none_or(|(from_block, _)| from_block > block);
+                if after {
+                    high = mid;
+                } else {
+                    #[allow(clippy::arithmetic_side_effects)]
+                    let next = mid + 1;
+                    low = next;
+                }
+            }
+            Ok(high
+                .checked_sub(1)
+                .and_then(|index| self.vote_checkpoints.get((account, index)))
+                .map_or(0, |(_, votes)| votes))
+        }
+
+        /// Creates `value` new tokens on the account `to`.
+        ///
+        /// A `Transfer` event without a sender is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotMintable` error if the token was deployed without minting.
+        ///
+        /// Returns `MissingRole` error if the caller is not a minter.
+        ///
+        /// Returns `Overflow` error if the total supply would exceed
+        /// `Balance::MAX`.
+        ///
+        /// Returns `CapExceeded` error if the total supply would exceed `cap`.
+        #[ink(message)]
+        pub fn mint(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            if !self.features.mintable {
+                return Err(Error::NotMintable)
+            }
+            self.ensure_role(Role::Minter)?;
+            let total_supply = self
+                .total_supply
+                .checked_add(value)
+                .ok_or(Error::Overflow)?;
+            if self.cap.is_some_and(|cap| total_supply > cap) {
+                return Err(Error::CapExceeded)
+            }
+            self.checkpoint_balance(&to);
+            self.checkpoint_total_supply();
+            let to_balance = self.balance_of_impl(&to);
+            // The balance is part of the total supply, so it cannot overflow either.
+            #[allow(clippy::arithmetic_side_effects)]
+            self.balances.insert(to, &(to_balance + value));
+            self.total_supply = total_supply;
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(to),
+                value,
+            });
+            self.move_votes(None, self.delegates.get(to), value);
+            Ok(())
+        }
+
+        /// Destroys `value` tokens from the caller's account.
+        ///
+        /// A `Transfer` event without a recipient is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotBurnable` error if the token was deployed without burning.
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the caller's account balance.
+        #[ink(message)]
+        pub fn burn(&mut self, value: Balance) -> Result<()> {
+            if !self.features.burnable {
+                return Err(Error::NotBurnable)
+            }
+            let caller = self.env().caller();
+            self.burn_impl(&caller, value)
+        }
+
+        /// Destroys `value` tokens from `account`, spending the caller's
+        /// allowance over them.
+        ///
+        /// A `Transfer` event without a recipient is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotBurnable` error if the token was deployed without burning.
+        ///
+        /// Returns `InsufficientAllowance` error if there are not enough tokens allowed
+        /// for the caller to burn from `account`.
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the account balance of `account`.
+        #[ink(message)]
+        pub fn burn_from(&mut self, account: AccountId, value: Balance) -> Result<()> {
+            if !self.features.burnable {
+                return Err(Error::NotBurnable)
+            }
+            let caller = self.env().caller();
+            let allowance = self.allowance_impl(&account, &caller);
+            if allowance < value {
+                return Err(Error::InsufficientAllowance)
+            }
+            self.burn_impl(&account, value)?;
+            // We checked that allowance >= value
+            #[allow(clippy::arithmetic_side_effects)]
+            self.allowances
+                .insert((&account, &caller), &(allowance - value));
+            Ok(())
+        }
+
+        /// Transfers `value` tokens on the behalf of `from` to the account `to`.
+        ///
+        /// This can be used to allow a contract to transfer tokens on ones behalf and/or
+        /// to charge fees in sub-currencies, for example.
+        ///
+        /// On success a `Transfer` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientAllowance` error if there are not enough tokens allowed
+        /// for the caller to withdraw from `from`.
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the account balance of `from`.
+        #[ink(message)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let caller = self.env().caller();
+            self.transfer_from_impl(&caller, &from, &to, value)
+        }
+
+        /// Sets the allowance of `spender` over `owner`'s tokens to `value`
+        /// and emits an `Approval` event.
+        ///
+        /// # Errors
+        ///
+        /// Returns `Paused` error if the token is paused.
+        fn approve_impl(
+            &mut self,
+            owner: &AccountId,
+            spender: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_not_paused()?;
+            self.allowances.insert((owner, spender), &value);
+            self.env().emit_event(Approval {
+                owner: *owner,
+                spender: *spender,
+                value,
+            });
+            Ok(())
+        }
+
+        fn increase_allowance_impl(
+            &mut self,
+            owner: &AccountId,
+            spender: &AccountId,
+            delta: Balance,
+        ) -> Result<()> {
+            let value = self
+                .allowance_impl(owner, spender)
+                .checked_add(delta)
+                .ok_or(Error::Overflow)?;
+            self.approve_impl(owner, spender, value)
+        }
+
+        fn decrease_allowance_impl(
+            &mut self,
+            owner: &AccountId,
+            spender: &AccountId,
+            delta: Balance,
+        ) -> Result<()> {
+            let value = self
+                .allowance_impl(owner, spender)
+                .checked_sub(delta)
+                .ok_or(Error::InsufficientAllowance)?;
+            self.approve_impl(owner, spender, value)
+        }
+
+        /// Transfers `value` tokens from `from` to `to`, spending `caller`'s
+        /// allowance over `from`'s tokens.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientAllowance` error if there are not enough tokens allowed
+        /// for `caller` to withdraw from `from`.
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the account balance of `from`.
+        fn transfer_from_impl(
+            &mut self,
+            caller: &AccountId,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_not_paused()?;
+            let allowance = self.allowance_impl(from, caller);
+            if allowance < value {
+                return Err(Error::InsufficientAllowance)
+            }
+            self.transfer_from_to(from, to, value)?;
+            // We checked that allowance >= value
+            #[allow(clippy::arithmetic_side_effects)]
+            self.allowances.insert((from, caller), &(allowance - value));
+            Ok(())
+        }
+
+        /// Transfers `value` amount of tokens from the caller's account to account `to`.
+        ///
+        /// On success a `Transfer` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `Paused` error if the token is paused.
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the caller's account balance.
+        fn transfer_from_to(
+            &mut self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_not_paused()?;
+            let from_balance = self.balance_of_impl(from);
+            if from_balance < value {
+                return Err(Error::InsufficientBalance)
+            }
+            self.checkpoint_balance(from);
+            self.checkpoint_balance(to);
+            // We checked that from_balance >= value
+            #[allow(clippy::arithmetic_side_effects)]
+            self.balances.insert(from, &(from_balance - value));
+            let to_balance = self.balance_of_impl(to);
+            self.balances
+                .insert(to, &(to_balance.checked_add(value).unwrap()));
+            self.env().emit_event(Transfer {
+                from: Some(*from),
+                to: Some(*to),
+                value,
+            });
+            self.move_votes(self.delegates.get(from), self.delegates.get(to), value);
+            Ok(())
+        }
+
+        /// Destroys `value` tokens from `account` and emits a `Transfer` event
+        /// without a recipient.
+        fn burn_impl(&mut self, account: &AccountId, value: Balance) -> Result<()> {
+            let balance = self.balance_of_impl(account);
+            if balance < value {
+                return Err(Error::InsufficientBalance)
+            }
+            self.checkpoint_balance(account);
+            self.checkpoint_total_supply();
+            // We checked that balance >= value, and the total supply includes
+            // the balance.
+            #[allow(clippy::arithmetic_side_effects)]
+            self.balances.insert(account, &(balance - value));
+            #[allow(clippy::arithmetic_side_effects)]
+            let total_supply = self.total_supply - value;
+            self.total_supply = total_supply;
+            self.env().emit_event(Transfer {
+                from: Some(*account),
+                to: None,
+                value,
+            });
+            self.move_votes(self.delegates.get(account), None, value);
+            Ok(())
+        }
+
+        /// Records the balance of `account` for the latest snapshot, unless it
+        /// changed since then already. Called before every balance change.
+        fn checkpoint_balance(&mut self, account: &AccountId) {
+            let id = self.snapshot_id;
+            let count = self.balance_checkpoint_count.get(account).unwrap_or_default();
+            let last_id = count
+                .checked_sub(1)
+                .and_then(|index| self.balance_checkpoints.get((*account, index)))
+                .map_or(0, |(last_id, _)| last_id);
+            if last_id < id {
+                let balance = self.balance_of_impl(account);
+                self.balance_checkpoints.insert((*account, count), &(id, balance));
+                // There is at most one checkpoint per snapshot id.
+                #[allow(clippy::arithmetic_side_effects)]
+                self.balance_checkpoint_count.insert(account, &(count + 1));
+            }
+        }
+
+        /// Records the total supply for the latest snapshot, unless it changed
+        /// since then already. Called before every total supply change.
+        fn checkpoint_total_supply(&mut self) {
+            let id = self.snapshot_id;
+            let count = self.total_supply_checkpoint_count;
+            let last_id = count
+                .checked_sub(1)
+                .and_then(|index| self.total_supply_checkpoints.get(index))
+                .map_or(0, |(last_id, _)| last_id);
+            if last_id < id {
+                self.total_supply_checkpoints
+                    .insert(count, &(id, self.total_supply));
+                // There is at most one checkpoint per snapshot id.
+                #[allow(clippy::arithmetic_side_effects)]
+                let next = count + 1;
+                self.total_supply_checkpoint_count = next;
+            }
+        }
+
+        /// Returns the value of the first of `count` checkpoints, read through
+        /// `checkpoint`, recorded for `snapshot_id` or later. `None` means the
+        /// value has not changed since `snapshot_id`.
+        fn checkpoint_at(
+            count: u32,
+            snapshot_id: u32,
+            checkpoint: impl Fn(u32) -> Option<Checkpoint>,
+        ) -> Option<Balance> {
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                // `low < high`, so neither can overflow.
+                #[allow(clippy::arithmetic_side_effects)]
+                let mid = low + (high - low) / 2;
+                let (id, _) = checkpoint(mid)?;
+                if id < snapshot_id {
+                    #[allow(clippy::arithmetic_side_effects)]
+                    let next = mid + 1;
+                    low = next;
+                } else {
+                    high = mid;
+                }
+            }
+            checkpoint(low).map(|(_, value)| value)
+        }
+
+        /// Moves `value` votes from delegate `from` to delegate `to`, where
+        /// `None` stands for tokens that are minted, burnt or not delegated.
+        fn move_votes(&mut self, from: Option<AccountId>, to: Option<AccountId>, value: Balance) {
+            if from == to || value == 0 {
+                return
+            }
+            // Votes are backed by balances, so they stay within the total supply.
+            if let Some(from) = from {
+                #[allow(clippy::arithmetic_side_effects)]
+                self.write_votes(from, |votes| votes - value);
+            }
+            if let Some(to) = to {
+                #[allow(clippy::arithmetic_side_effects)]
+                self.write_votes(to, |votes| votes + value);
+            }
+        }
+
+        /// Records the votes `change` makes of `delegate`'s current votes from
+        /// the current block on, and emits a `DelegateVotesChanged` event.
+        fn write_votes(&mut self, delegate: AccountId, change: impl FnOnce(Balance) -> Balance) {
+            let block = self.env().block_number();
+            let count = self.vote_checkpoint_count.get(delegate).unwrap_or_default();
+            let last = count.checked_sub(1).and_then(|index| {
+                self.vote_checkpoints
+                    .get((delegate, index))
+                    .map(|checkpoint| (index, checkpoint))
+            });
+            let previous_votes = last.map_or(0, |(_, (_, votes))| votes);
+            let new_votes = change(previous_votes);
+            match last {
+                // A block keeps only its final votes.
+                Some((index, (from_block, _))) if from_block == block => {
+                    self.vote_checkpoints
+                        .insert((delegate, index), &(block, new_votes));
+                }
+                _ => {
+                    self.vote_checkpoints
+                        .insert((delegate, count), &(block, new_votes));
+                    // There is at most one checkpoint per block.
+                    #[allow(clippy::arithmetic_side_effects)]
+                    self.vote_checkpoint_count.insert(delegate, &(count + 1));
+                }
+            }
+            self.env().emit_event(DelegateVotesChanged {
+                delegate,
+                previous_votes,
+                new_votes,
+            });
+        }
+
+        fn ensure_snapshot_exists(&self, snapshot_id: u32) -> Result<()> {
+            if snapshot_id == 0 || snapshot_id > self.snapshot_id {
+                return Err(Error::InvalidSnapshotId)
+            }
+            Ok(())
+        }
+
+        fn remove_role(&mut self, role: Role, account: AccountId) {
+            if self.roles.take((role, account)).is_some() {
+                self.env().emit_event(RoleRevoked {
+                    role,
+                    account,
+                    sender: self.env().caller(),
+                });
+            }
+        }
+
+        fn ensure_not_paused(&self) -> Result<()> {
+            if self.paused {
+                return Err(Error::Paused)
+            }
+            Ok(())
+        }
+
+        fn ensure_role(&self, role: Role) -> Result<()> {
+            if !self.has_role(role, self.env().caller()) {
+                return Err(Error::MissingRole)
+            }
+            Ok(())
+        }
+    }
+
+    impl PSP22 for Erc20 {
+        #[ink(message)]
+        fn total_supply(&self) -> Balance {
+            self.total_supply
+        }
+
+        #[ink(message)]
+        fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balance_of_impl(&owner)
+        }
+
+        #[ink(message)]
+        fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowance_impl(&owner, &spender)
+        }
+
+        #[ink(message)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let from = self.env().caller();
+            Ok(self.transfer_from_to(&from, &to, value)?)
+        }
+
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            Ok(self.transfer_from_impl(&caller, &from, &to, value)?)
+        }
+
+        #[ink(message)]
+        fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            Ok(self.approve_impl(&owner, &spender, value)?)
+        }
+
+        #[ink(message)]
+        fn increase_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            Ok(self.increase_allowance_impl(&owner, &spender, delta_value)?)
+        }
+
+        #[ink(message)]
+        fn decrease_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            Ok(self.decrease_allowance_impl(&owner, &spender, delta_value)?)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use ink::primitives::{
+            Clear,
+            Hash,
+        };
+
+        fn assert_transfer_event(
+            event: &ink::env::test::EmittedEvent,
+            expected_from: Option<AccountId>,
+            expected_to: Option<AccountId>,
+            expected_value: Balance,
+        ) {
+            let decoded_event =
+                <Transfer as ink::scale::Decode>::decode(&mut &event.data[..])
+                    .expect("encountered invalid contract event data buffer");
+            let Transfer { from, to, value } = decoded_event;
+            assert_eq!(from, expected_from, "encountered invalid Transfer.from");
+            assert_eq!(to, expected_to, "encountered invalid Transfer.to");
+            assert_eq!(value, expected_value, "encountered invalid Trasfer.value");
 
             let mut expected_topics = Vec::new();
             expected_topics.push(
                 ink::blake2x256!("Transfer(Option<AccountId>,Option<AccountId>,Balance)")
                     .into(),
             );
-            if let Some(from) = expected_from {
-                expected_topics.push(encoded_into_hash(from));
-            } else {
-                expected_topics.push(Hash::CLEAR_HASH);
-            }
-            if let Some(to) = expected_to {
-                expected_topics.push(encoded_into_hash(to));
-            } else {
-                expected_topics.push(Hash::CLEAR_HASH);
+            if let Some(from) = expected_from {
+                expected_topics.push(encoded_into_hash(from));
+            } else {
+                expected_topics.push(Hash::CLEAR_HASH);
+            }
+            if let Some(to) = expected_to {
+                expected_topics.push(encoded_into_hash(to));
+            } else {
+                expected_topics.push(Hash::CLEAR_HASH);
+            }
+            expected_topics.push(encoded_into_hash(value));
+
+            let topics = event.topics.clone();
+            for (n, (actual_topic, expected_topic)) in
+                topics.iter().zip(expected_topics).enumerate()
+            {
+                let mut topic_hash = Hash::CLEAR_HASH;
+                let len = actual_topic.len();
+                topic_hash.as_mut()[0..len].copy_from_slice(&actual_topic[0..len]);
+
+                assert_eq!(
+                    topic_hash, expected_topic,
+                    "encountered invalid topic at {n}"
+                );
+            }
+        }
+
+        /// The default constructor does its job.
+        #[ink::test]
+        fn new_works() {
+            // Constructor works.
+            let _erc20 = Erc20::new(100, None, None, None, 18);
+
+            // Transfer event triggered during initial construction.
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(1, emitted_events.len());
+
+            assert_transfer_event(
+                &emitted_events[0],
+                None,
+                Some(AccountId::from([0x01; 32])),
+                100,
+            );
+        }
+
+        /// The total supply was applied.
+        #[ink::test]
+        fn total_supply_works() {
+            // Constructor works.
+            let erc20 = Erc20::new(100, None, None, None, 18);
+            // Transfer event triggered during initial construction.
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_transfer_event(
+                &emitted_events[0],
+                None,
+                Some(AccountId::from([0x01; 32])),
+                100,
+            );
+            // Get the token total supply.
+            assert_eq!(erc20.total_supply(), 100);
+        }
+
+        /// Get the actual balance of an account.
+        #[ink::test]
+        fn balance_of_works() {
+            // Constructor works
+            let erc20 = Erc20::new(100, None, None, None, 18);
+            // Transfer event triggered during initial construction
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_transfer_event(
+                &emitted_events[0],
+                None,
+                Some(AccountId::from([0x01; 32])),
+                100,
+            );
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Alice owns all the tokens on contract instantiation
+            assert_eq!(erc20.balance_of(accounts.alice), 100);
+            // Bob does not owns tokens
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn transfer_works() {
+            // Constructor works.
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            // Transfer event triggered during initial construction.
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            // Alice transfers 10 tokens to Bob.
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            // Bob owns 10 tokens.
+            assert_eq!(erc20.balance_of(accounts.bob), 10);
+
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 2);
+            // Check first transfer event related to ERC-20 instantiation.
+            assert_transfer_event(
+                &emitted_events[0],
+                None,
+                Some(AccountId::from([0x01; 32])),
+                100,
+            );
+            // Check the second transfer event relating to the actual trasfer.
+            assert_transfer_event(
+                &emitted_events[1],
+                Some(AccountId::from([0x01; 32])),
+                Some(AccountId::from([0x02; 32])),
+                10,
+            );
+        }
+
+        #[ink::test]
+        fn invalid_transfer_should_fail() {
+            // Constructor works.
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+
+            // Set the contract as callee and Bob as caller.
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+
+            // Bob fails to transfers 10 tokens to Eve.
+            assert_eq!(
+                erc20.transfer(accounts.eve, 10),
+                Err(Error::InsufficientBalance)
+            );
+            // Alice owns all the tokens.
+            assert_eq!(erc20.balance_of(accounts.alice), 100);
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            assert_eq!(erc20.balance_of(accounts.eve), 0);
+
+            // Transfer event triggered during initial construction.
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 1);
+            assert_transfer_event(
+                &emitted_events[0],
+                None,
+                Some(AccountId::from([0x01; 32])),
+                100,
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_works() {
+            // Constructor works.
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            // Transfer event triggered during initial construction.
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Bob fails to transfer tokens owned by Alice.
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.eve, 10),
+                Err(Error::InsufficientAllowance)
+            );
+            // Alice approves Bob for token transfers on her behalf.
+            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+
+            // The approve event takes place.
+            assert_eq!(ink::env::test::recorded_events().count(), 2);
+
+            // Set the contract as callee and Bob as caller.
+            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+
+            // Bob transfers tokens from Alice to Eve.
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.eve, 10),
+                Ok(())
+            );
+            // Eve owns tokens.
+            assert_eq!(erc20.balance_of(accounts.eve), 10);
+
+            // Check all transfer events that happened during the previous calls:
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 3);
+            assert_transfer_event(
+                &emitted_events[0],
+                None,
+                Some(AccountId::from([0x01; 32])),
+                100,
+            );
+            // The second event `emitted_events[1]` is an Approve event that we skip
+            // checking.
+            assert_transfer_event(
+                &emitted_events[2],
+                Some(AccountId::from([0x01; 32])),
+                Some(AccountId::from([0x05; 32])),
+                10,
+            );
+        }
+
+        #[ink::test]
+        fn allowance_must_not_change_on_failed_transfer() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Alice approves Bob for token transfers on her behalf.
+            let alice_balance = erc20.balance_of(accounts.alice);
+            let initial_allowance = alice_balance + 2;
+            assert_eq!(erc20.approve(accounts.bob, initial_allowance), Ok(()));
+
+            // Get contract address.
+            let callee = ink::env::account_id::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(callee);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+
+            // Bob tries to transfer tokens from Alice to Eve.
+            let emitted_events_before = ink::env::test::recorded_events().count();
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.eve, alice_balance + 1),
+                Err(Error::InsufficientBalance)
+            );
+            // Allowance must have stayed the same
+            assert_eq!(
+                erc20.allowance(accounts.alice, accounts.bob),
+                initial_allowance
+            );
+            // No more events must have been emitted
+            assert_eq!(
+                emitted_events_before,
+                ink::env::test::recorded_events().count()
+            )
+        }
+
+        #[ink::test]
+        fn metadata_is_set_at_construction() {
+            let erc20 = Erc20::new(
+                100,
+                Some(String::from("Token")),
+                Some(String::from("TKN")),
+                Some(String::from("ipfs://logo")),
+                12,
+            );
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.token_name(), Some(String::from("Token")));
+            assert_eq!(erc20.token_symbol(), Some(String::from("TKN")));
+            assert_eq!(erc20.token_decimals(), 12);
+            assert_eq!(erc20.logo_uri(), Some(String::from("ipfs://logo")));
+            assert!(erc20.has_role(Role::Admin, accounts.alice));
+        }
+
+        #[ink::test]
+        fn admin_updates_metadata() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+
+            assert_eq!(
+                erc20.set_metadata(
+                    Some(String::from("Token")),
+                    Some(String::from("TKN")),
+                    None
+                ),
+                Ok(())
+            );
+
+            assert_eq!(erc20.token_name(), Some(String::from("Token")));
+            assert_eq!(erc20.token_symbol(), Some(String::from("TKN")));
+            assert_eq!(erc20.logo_uri(), None);
+            assert_eq!(erc20.token_decimals(), 18);
+            assert_eq!(ink::env::test::recorded_events().count(), 2);
+        }
+
+        #[ink::test]
+        fn only_admin_updates_metadata() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.set_metadata(Some(String::from("Token")), None, None),
+                Err(Error::MissingRole)
+            );
+            assert_eq!(
+                erc20.grant_role(Role::Admin, accounts.bob),
+                Err(Error::MissingRole)
+            );
+            assert_eq!(erc20.token_name(), None);
+        }
+
+        #[ink::test]
+        fn new_admin_updates_metadata() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.grant_role(Role::Admin, accounts.bob), Ok(()));
+            assert_eq!(erc20.renounce_role(Role::Admin), Ok(()));
+            assert!(erc20.has_role(Role::Admin, accounts.bob));
+            assert!(!erc20.has_role(Role::Admin, accounts.alice));
+
+            assert_eq!(
+                erc20.set_metadata(None, None, Some(String::from("ipfs://logo"))),
+                Err(Error::MissingRole)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.set_metadata(None, None, Some(String::from("ipfs://logo"))),
+                Ok(())
+            );
+            assert_eq!(erc20.logo_uri(), Some(String::from("ipfs://logo")));
+        }
+
+        #[ink::test]
+        fn increase_and_decrease_allowance_work() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.increase_allowance(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.increase_allowance(accounts.bob, 5), Ok(()));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 15);
+            assert_eq!(erc20.decrease_allowance(accounts.bob, 15), Ok(()));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+
+            // Constructor transfer plus one approval per change.
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 4);
+            let last = <Approval as ink::scale::Decode>::decode(
+                &mut &emitted_events[3].data[..],
+            )
+            .expect("encountered invalid contract event data buffer");
+            assert_eq!(last.owner, accounts.alice);
+            assert_eq!(last.spender, accounts.bob);
+            assert_eq!(last.value, 0);
+        }
+
+        #[ink::test]
+        fn decrease_allowance_below_zero_fails() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+            let emitted_events_before = ink::env::test::recorded_events().count();
+            assert_eq!(
+                erc20.decrease_allowance(accounts.bob, 11),
+                Err(Error::InsufficientAllowance)
+            );
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 10);
+            assert_eq!(
+                emitted_events_before,
+                ink::env::test::recorded_events().count()
+            );
+        }
+
+        #[ink::test]
+        fn increase_allowance_overflow_fails() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.approve(accounts.bob, Balance::MAX), Ok(()));
+            assert_eq!(
+                erc20.increase_allowance(accounts.bob, 1),
+                Err(Error::Overflow)
+            );
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), Balance::MAX);
+        }
+
+        #[ink::test]
+        fn mint_works() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.mint(accounts.bob, 50), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 50);
+            assert_eq!(erc20.total_supply(), 150);
+
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 2);
+            assert_transfer_event(
+                &emitted_events[1],
+                None,
+                Some(AccountId::from([0x02; 32])),
+                50,
+            );
+        }
+
+        #[ink::test]
+        fn mint_is_restricted_and_checked() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.mint(accounts.bob, Balance::MAX), Err(Error::Overflow));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.mint(accounts.bob, 50), Err(Error::MissingRole));
+            assert_eq!(erc20.total_supply(), 100);
+        }
+
+        #[ink::test]
+        fn burn_works() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.burn(30), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 70);
+            assert_eq!(erc20.total_supply(), 70);
+            assert_eq!(erc20.burn(71), Err(Error::InsufficientBalance));
+
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 2);
+            assert_transfer_event(
+                &emitted_events[1],
+                Some(AccountId::from([0x01; 32])),
+                None,
+                30,
+            );
+        }
+
+        #[ink::test]
+        fn burn_from_spends_allowance() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.approve(accounts.bob, 20), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.burn_from(accounts.alice, 21),
+                Err(Error::InsufficientAllowance)
+            );
+            assert_eq!(erc20.burn_from(accounts.alice, 15), Ok(()));
+
+            assert_eq!(erc20.balance_of(accounts.alice), 85);
+            assert_eq!(erc20.total_supply(), 85);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 5);
+        }
+
+        #[ink::test]
+        fn disabled_features_block_mint_and_burn() {
+            let features = Features {
+                mintable: false,
+                burnable: false,
+            };
+            let mut erc20 = Erc20::new_with_features(100, None, features, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.features(), features);
+            assert_eq!(erc20.approve(accounts.bob, 20), Ok(()));
+
+            assert_eq!(erc20.mint(accounts.alice, 1), Err(Error::NotMintable));
+            assert_eq!(erc20.burn(1), Err(Error::NotBurnable));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.burn_from(accounts.alice, 1), Err(Error::NotBurnable));
+            assert_eq!(erc20.total_supply(), 100);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 20);
+        }
+
+        #[ink::test]
+        fn burnable_token_cannot_be_minted() {
+            let features = Features {
+                mintable: false,
+                burnable: true,
+            };
+            let mut erc20 = Erc20::new_with_features(100, None, features, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.burn(30), Ok(()));
+            assert_eq!(erc20.mint(accounts.alice, 30), Err(Error::NotMintable));
+            assert_eq!(erc20.total_supply(), 70);
+            assert_eq!(Erc20::new(100, None, None, None, 18).features(), ALL_FEATURES);
+        }
+
+        #[ink::test]
+        fn admin_cannot_grant_minter_on_unmintable_token() {
+            let features = Features {
+                mintable: false,
+                burnable: false,
+            };
+            let mut erc20 = Erc20::new_with_features(100, None, features, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert!(erc20.has_role(Role::Admin, accounts.alice));
+            assert!(!erc20.has_role(Role::Minter, accounts.alice));
+
+            assert_eq!(
+                erc20.grant_role(Role::Minter, accounts.alice),
+                Err(Error::NotMintable)
+            );
+            assert!(!erc20.has_role(Role::Minter, accounts.alice));
+            assert_eq!(erc20.mint(accounts.alice, 1), Err(Error::NotMintable));
+            assert_eq!(erc20.grant_role(Role::Pauser, accounts.bob), Ok(()));
+        }
+
+        #[ink::test]
+        fn deployer_holds_every_role() {
+            let erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            for role in [Role::Admin, Role::Minter, Role::Pauser] {
+                assert!(erc20.has_role(role, accounts.alice));
+                assert!(!erc20.has_role(role, accounts.bob));
             }
-            expected_topics.push(encoded_into_hash(value));
+        }
+
+        #[ink::test]
+        fn admin_grants_and_revokes_roles() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.grant_role(Role::Minter, accounts.bob), Ok(()));
+            // Granting a held role again changes nothing.
+            assert_eq!(erc20.grant_role(Role::Minter, accounts.bob), Ok(()));
+            assert!(erc20.has_role(Role::Minter, accounts.bob));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.mint(accounts.bob, 5), Ok(()));
+            assert_eq!(
+                erc20.revoke_role(Role::Minter, accounts.alice),
+                Err(Error::MissingRole)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(erc20.revoke_role(Role::Minter, accounts.bob), Ok(()));
+            assert!(!erc20.has_role(Role::Minter, accounts.bob));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.mint(accounts.bob, 5), Err(Error::MissingRole));
+
+            // Constructor transfer, grant, mint and revoke.
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(emitted_events.len(), 4);
+            let granted = <RoleGranted as ink::scale::Decode>::decode(
+                &mut &emitted_events[1].data[..],
+            )
+            .expect("encountered invalid contract event data buffer");
+            assert_eq!(granted.role, Role::Minter);
+            assert_eq!(granted.account, accounts.bob);
+            assert_eq!(granted.sender, accounts.alice);
+        }
+
+        #[ink::test]
+        fn renounced_role_is_gone() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.renounce_role(Role::Minter), Ok(()));
+            assert!(!erc20.has_role(Role::Minter, accounts.alice));
+            assert_eq!(erc20.mint(accounts.alice, 1), Err(Error::MissingRole));
+            assert_eq!(ink::env::test::recorded_events().count(), 2);
+        }
+
+        #[ink::test]
+        fn pause_blocks_transfers_and_approvals() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+
+            assert_eq!(erc20.pause(), Ok(()));
+            assert!(erc20.paused());
+            assert_eq!(erc20.pause(), Err(Error::Paused));
+            assert_eq!(erc20.transfer(accounts.bob, 1), Err(Error::Paused));
+            assert_eq!(erc20.approve(accounts.bob, 1), Err(Error::Paused));
+            assert_eq!(erc20.increase_allowance(accounts.bob, 1), Err(Error::Paused));
+            assert_eq!(
+                PSP22::transfer(&mut erc20, accounts.bob, 1, Vec::new()),
+                Err(PSP22Error::Custom(String::from("Paused")))
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 1),
+                Err(Error::Paused)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(erc20.unpause(), Ok(()));
+            assert!(!erc20.paused());
+            assert_eq!(erc20.unpause(), Err(Error::NotPaused));
+            assert_eq!(erc20.transfer(accounts.bob, 1), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 1);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 10);
+        }
 
-            let topics = event.topics.clone();
-            for (n, (actual_topic, expected_topic)) in
-                topics.iter().zip(expected_topics).enumerate()
-            {
-                let mut topic_hash = Hash::CLEAR_HASH;
-                let len = actual_topic.len();
-                topic_hash.as_mut()[0..len].copy_from_slice(&actual_topic[0..len]);
+        #[ink::test]
+        fn only_pauser_pauses() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
 
-                assert_eq!(
-                    topic_hash, expected_topic,
-                    "encountered invalid topic at {n}"
-                );
-            }
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.pause(), Err(Error::MissingRole));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(erc20.grant_role(Role::Pauser, accounts.bob), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.pause(), Ok(()));
+            assert_eq!(erc20.unpause(), Ok(()));
+        }
+
+        /// Returns an owner account backed by a real ECDSA key, and a signer
+        /// for it.
+        fn permit_owner() -> (AccountId, impl Fn([u8; 32]) -> PermitSignature) {
+            let secret_key = secp256k1::SecretKey::from_slice(&[0x42; 32]).unwrap();
+            let public_key = secret_key.public_key(secp256k1::SECP256K1).serialize();
+            let mut owner = [0u8; 32];
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut owner);
+            (AccountId::from(owner), move |message: [u8; 32]| {
+                let (recovery_id, compact) = secp256k1::SECP256K1
+                    .sign_ecdsa_recoverable(
+                        &secp256k1::Message::from_digest(message),
+                        &secret_key,
+                    )
+                    .serialize_compact();
+                let mut signature = [0u8; 65];
+                signature[..64].copy_from_slice(&compact);
+                signature[64] = recovery_id.to_i32() as u8;
+                PermitSignature::Ecdsa(signature)
+            })
+        }
+
+        /// Returns an owner account backed by a real sr25519 key, and a signer
+        /// for it.
+        fn sr25519_permit_owner() -> (AccountId, impl Fn([u8; 32]) -> PermitSignature) {
+            let keypair = ink_e2e::alice();
+            let owner = AccountId::from(keypair.public_key().0);
+            (owner, move |message: [u8; 32]| {
+                PermitSignature::Sr25519(keypair.sign(&message).0)
+            })
         }
 
-        /// The default constructor does its job.
         #[ink::test]
-        fn new_works() {
-            // Constructor works.
-            let _erc20 = Erc20::new(100);
+        fn permit_approves_spender() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let (owner, sign) = permit_owner();
+            let signature = sign(erc20.permit_message(owner, accounts.bob, 50, 1_000));
 
-            // Transfer event triggered during initial construction.
-            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(1, emitted_events.len());
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 50, 1_000, signature.clone()),
+                Ok(())
+            );
+            assert_eq!(erc20.allowance(owner, accounts.bob), 50);
+            assert_eq!(erc20.nonces(owner), 1);
 
-            assert_transfer_event(
-                &emitted_events[0],
-                None,
-                Some(AccountId::from([0x01; 32])),
-                100,
+            // The nonce moved on, so the same signature does not work twice.
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 50, 1_000, signature),
+                Err(Error::InvalidSignature)
             );
         }
 
-        /// The total supply was applied.
         #[ink::test]
-        fn total_supply_works() {
-            // Constructor works.
-            let erc20 = Erc20::new(100);
-            // Transfer event triggered during initial construction.
-            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            assert_transfer_event(
-                &emitted_events[0],
-                None,
-                Some(AccountId::from([0x01; 32])),
-                100,
+        fn permit_after_deadline_fails() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let (owner, sign) = permit_owner();
+            let signature = sign(erc20.permit_message(owner, accounts.bob, 50, 1_000));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_001);
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 50, 1_000, signature),
+                Err(Error::PermitExpired)
             );
-            // Get the token total supply.
-            assert_eq!(erc20.total_supply(), 100);
+            assert_eq!(erc20.allowance(owner, accounts.bob), 0);
         }
 
-        /// Get the actual balance of an account.
         #[ink::test]
-        fn balance_of_works() {
-            // Constructor works
-            let erc20 = Erc20::new(100);
-            // Transfer event triggered during initial construction
-            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            assert_transfer_event(
-                &emitted_events[0],
-                None,
-                Some(AccountId::from([0x01; 32])),
-                100,
-            );
+        fn permit_rejects_other_signatures() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            // Alice owns all the tokens on contract instantiation
-            assert_eq!(erc20.balance_of(accounts.alice), 100);
-            // Bob does not owns tokens
-            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            let (owner, sign) = permit_owner();
+
+            // Signed for another value.
+            let signature = sign(erc20.permit_message(owner, accounts.bob, 50, 1_000));
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 60, 1_000, signature.clone()),
+                Err(Error::InvalidSignature)
+            );
+            // Signed by someone else than the owner.
+            assert_eq!(
+                erc20.permit(accounts.alice, accounts.bob, 50, 1_000, signature),
+                Err(Error::InvalidSignature)
+            );
+            assert_eq!(
+                erc20.permit(
+                    owner,
+                    accounts.bob,
+                    50,
+                    1_000,
+                    PermitSignature::Ecdsa([0; 65])
+                ),
+                Err(Error::InvalidSignature)
+            );
+            assert_eq!(erc20.nonces(owner), 0);
         }
 
+        #[cfg(feature = "sr25519")]
         #[ink::test]
-        fn transfer_works() {
-            // Constructor works.
-            let mut erc20 = Erc20::new(100);
-            // Transfer event triggered during initial construction.
+        fn sr25519_permit_approves_spender() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let (owner, sign) = sr25519_permit_owner();
+            let signature = sign(erc20.permit_message(owner, accounts.bob, 50, 1_000));
 
-            assert_eq!(erc20.balance_of(accounts.bob), 0);
-            // Alice transfers 10 tokens to Bob.
-            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
-            // Bob owns 10 tokens.
-            assert_eq!(erc20.balance_of(accounts.bob), 10);
-
-            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(emitted_events.len(), 2);
-            // Check first transfer event related to ERC-20 instantiation.
-            assert_transfer_event(
-                &emitted_events[0],
-                None,
-                Some(AccountId::from([0x01; 32])),
-                100,
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 50, 1_000, signature.clone()),
+                Ok(())
             );
-            // Check the second transfer event relating to the actual trasfer.
-            assert_transfer_event(
-                &emitted_events[1],
-                Some(AccountId::from([0x01; 32])),
-                Some(AccountId::from([0x02; 32])),
-                10,
+            assert_eq!(erc20.allowance(owner, accounts.bob), 50);
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 50, 1_000, signature),
+                Err(Error::InvalidSignature)
             );
         }
 
+        #[cfg(not(feature = "sr25519"))]
         #[ink::test]
-        fn invalid_transfer_should_fail() {
-            // Constructor works.
-            let mut erc20 = Erc20::new(100);
+        fn sr25519_permits_are_unsupported_by_default() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let (owner, sign) = sr25519_permit_owner();
+            let signature = sign(erc20.permit_message(owner, accounts.bob, 50, 1_000));
 
-            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 50, 1_000, signature),
+                Err(Error::UnsupportedSignature)
+            );
+            assert_eq!(erc20.allowance(owner, accounts.bob), 0);
+            assert_eq!(erc20.nonces(owner), 0);
+        }
 
-            // Set the contract as callee and Bob as caller.
-            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
-            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract);
+        #[ink::test]
+        fn delegating_gives_votes() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.get_votes(accounts.alice), 0);
+
+            assert_eq!(erc20.delegate(accounts.alice), Ok(()));
+            assert_eq!(erc20.delegates(accounts.alice), Some(accounts.alice));
+            assert_eq!(erc20.get_votes(accounts.alice), 100);
+
+            // Redelegating moves every vote.
+            assert_eq!(erc20.delegate(accounts.bob), Ok(()));
+            assert_eq!(erc20.get_votes(accounts.alice), 0);
+            assert_eq!(erc20.get_votes(accounts.bob), 100);
+
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            // Constructor transfer, then per delegation one `DelegateChanged` and
+            // one `DelegateVotesChanged` per affected delegate.
+            assert_eq!(emitted_events.len(), 6);
+            let changed = <DelegateChanged as ink::scale::Decode>::decode(
+                &mut &emitted_events[3].data[..],
+            )
+            .expect("encountered invalid contract event data buffer");
+            assert_eq!(changed.delegator, accounts.alice);
+            assert_eq!(changed.from_delegate, Some(accounts.alice));
+            assert_eq!(changed.to_delegate, accounts.bob);
+            let votes = <DelegateVotesChanged as ink::scale::Decode>::decode(
+                &mut &emitted_events[5].data[..],
+            )
+            .expect("encountered invalid contract event data buffer");
+            assert_eq!(votes.delegate, accounts.bob);
+            assert_eq!(votes.previous_votes, 0);
+            assert_eq!(votes.new_votes, 100);
+        }
+
+        #[ink::test]
+        fn balance_changes_move_votes() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.delegate(accounts.alice), Ok(()));
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.delegate(accounts.charlie), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+
+            assert_eq!(erc20.transfer(accounts.bob, 30), Ok(()));
+            assert_eq!(erc20.get_votes(accounts.alice), 70);
+            assert_eq!(erc20.get_votes(accounts.charlie), 30);
+
+            assert_eq!(erc20.mint(accounts.bob, 20), Ok(()));
+            assert_eq!(erc20.get_votes(accounts.charlie), 50);
+            assert_eq!(erc20.burn(10), Ok(()));
+            assert_eq!(erc20.get_votes(accounts.alice), 60);
+
+            // Tokens of holders without a delegate do not vote.
+            assert_eq!(erc20.transfer(accounts.eve, 60), Ok(()));
+            assert_eq!(erc20.get_votes(accounts.alice), 0);
+            assert_eq!(erc20.get_votes(accounts.eve), 0);
+        }
 
-            // Bob fails to transfers 10 tokens to Eve.
+        #[ink::test]
+        fn past_votes_are_kept_per_block() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let start = ink::env::block_number::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.delegate(accounts.alice), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.transfer(accounts.bob, 40), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.get_past_votes(accounts.alice, start), Ok(100));
+            assert_eq!(erc20.get_past_votes(accounts.alice, start + 1), Ok(50));
+            assert_eq!(erc20.get_past_votes(accounts.alice, start + 2), Ok(50));
+            assert_eq!(erc20.get_past_votes(accounts.bob, start + 2), Ok(0));
             assert_eq!(
-                erc20.transfer(accounts.eve, 10),
-                Err(Error::InsufficientBalance)
+                erc20.get_past_votes(accounts.alice, start + 3),
+                Err(Error::FutureLookup)
             );
-            // Alice owns all the tokens.
-            assert_eq!(erc20.balance_of(accounts.alice), 100);
-            assert_eq!(erc20.balance_of(accounts.bob), 0);
-            assert_eq!(erc20.balance_of(accounts.eve), 0);
+        }
 
-            // Transfer event triggered during initial construction.
-            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(emitted_events.len(), 1);
-            assert_transfer_event(
-                &emitted_events[0],
-                None,
-                Some(AccountId::from([0x01; 32])),
-                100,
-            );
+        #[ink::test]
+        fn snapshots_keep_past_balances() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.snapshot(), Ok(1));
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 5), Ok(()));
+            assert_eq!(erc20.snapshot(), Ok(2));
+            assert_eq!(erc20.snapshot(), Ok(3));
+            assert_eq!(erc20.transfer(accounts.bob, 20), Ok(()));
+
+            assert_eq!(erc20.balance_of_at(accounts.alice, 1), Ok(100));
+            assert_eq!(erc20.balance_of_at(accounts.bob, 1), Ok(0));
+            assert_eq!(erc20.balance_of_at(accounts.alice, 2), Ok(85));
+            assert_eq!(erc20.balance_of_at(accounts.bob, 2), Ok(15));
+            assert_eq!(erc20.balance_of_at(accounts.bob, 3), Ok(15));
+            assert_eq!(erc20.balance_of(accounts.bob), 35);
+            // Untouched accounts read their current balance.
+            assert_eq!(erc20.balance_of_at(accounts.eve, 3), Ok(0));
+            assert_eq!(erc20.current_snapshot_id(), 3);
         }
 
         #[ink::test]
-        fn transfer_from_works() {
-            // Constructor works.
-            let mut erc20 = Erc20::new(100);
-            // Transfer event triggered during initial construction.
+        fn snapshots_keep_past_total_supply() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(erc20.snapshot(), Ok(1));
+            assert_eq!(erc20.mint(accounts.bob, 50), Ok(()));
+            assert_eq!(erc20.snapshot(), Ok(2));
+            assert_eq!(erc20.burn(30), Ok(()));
+
+            assert_eq!(erc20.total_supply_at(1), Ok(100));
+            assert_eq!(erc20.total_supply_at(2), Ok(150));
+            assert_eq!(erc20.total_supply(), 120);
+            assert_eq!(erc20.balance_of_at(accounts.bob, 1), Ok(0));
+            assert_eq!(erc20.balance_of_at(accounts.alice, 2), Ok(100));
+            assert_eq!(erc20.balance_of(accounts.alice), 70);
+        }
+
+        #[ink::test]
+        fn unknown_snapshots_are_rejected() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
 
-            // Bob fails to transfer tokens owned by Alice.
             assert_eq!(
-                erc20.transfer_from(accounts.alice, accounts.eve, 10),
-                Err(Error::InsufficientAllowance)
+                erc20.balance_of_at(accounts.alice, 0),
+                Err(Error::InvalidSnapshotId)
             );
-            // Alice approves Bob for token transfers on her behalf.
-            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
-
-            // The approve event takes place.
-            assert_eq!(ink::env::test::recorded_events().count(), 2);
+            assert_eq!(erc20.total_supply_at(1), Err(Error::InvalidSnapshotId));
 
-            // Set the contract as callee and Bob as caller.
-            let contract = ink::env::account_id::<ink::env::DefaultEnvironment>();
-            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(contract);
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(erc20.snapshot(), Err(Error::MissingRole));
+            assert_eq!(erc20.current_snapshot_id(), 0);
+        }
+
+        #[ink::test]
+        fn uncapped_token_has_no_cap() {
+            let erc20 = Erc20::new(100, None, None, None, 18);
+            assert_eq!(erc20.cap(), None);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Initial supply exceeds the cap")]
+        fn initial_supply_above_cap_fails() {
+            let _ = Erc20::new_capped(101, 100, None, None, None, 18);
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "Initial supply exceeds the cap")]
+        fn initial_supply_above_feature_cap_fails() {
+            let _ = Erc20::new_with_features(101, Some(100), ALL_FEATURES, None, None, None, 18);
+        }
+
+        #[ink::test]
+        fn mint_stops_at_cap() {
+            let mut erc20 = Erc20::new_capped(100, 150, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.cap(), Some(150));
+
+            assert_eq!(erc20.mint(accounts.bob, 50), Ok(()));
+            assert_eq!(erc20.total_supply(), 150);
+            assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::CapExceeded));
+            assert_eq!(erc20.balance_of(accounts.bob), 50);
+
+            // Burnt tokens free room under the cap again.
+            assert_eq!(erc20.burn(10), Ok(()));
+            assert_eq!(erc20.mint(accounts.bob, 11), Err(Error::CapExceeded));
+            assert_eq!(erc20.mint(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.total_supply(), 150);
+        }
+
+        #[ink::test]
+        fn total_supply_never_exceeds_cap() {
+            let cap = 1_000;
+            let mut erc20 = Erc20::new_capped(cap, cap, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert!(erc20.total_supply() <= cap);
+
+            for amount in [0, 1, 7, 100, 999, 1_000, Balance::MAX] {
+                assert_eq!(erc20.burn(amount % 300), Ok(()));
+                let _ = erc20.mint(accounts.bob, amount);
+                assert!(erc20.total_supply() <= cap);
+            }
+        }
+
+        #[ink::test]
+        fn psp22_selectors_match_the_standard() {
+            assert_eq!(ink::selector_id!("PSP22::total_supply"), 0x162df8c2);
+            assert_eq!(ink::selector_id!("PSP22::balance_of"), 0x6568382f);
+            assert_eq!(ink::selector_id!("PSP22::allowance"), 0x4d47d921);
+            assert_eq!(ink::selector_id!("PSP22::transfer"), 0xdb20f9f5);
+            assert_eq!(ink::selector_id!("PSP22::transfer_from"), 0x54b3c76e);
+            assert_eq!(ink::selector_id!("PSP22::approve"), 0xb20f1bbd);
+            assert_eq!(ink::selector_id!("PSP22::increase_allowance"), 0x96d6b57a);
+            assert_eq!(ink::selector_id!("PSP22::decrease_allowance"), 0xfecb57d5);
+            assert_eq!(ink::selector_id!("PSP22Metadata::token_name"), 0x3d261bd4);
+            assert_eq!(ink::selector_id!("PSP22Metadata::token_symbol"), 0x34205be5);
+            assert_eq!(ink::selector_id!("PSP22Metadata::token_decimals"), 0x7271b782);
+        }
+
+        #[ink::test]
+        fn psp22_transfer_works() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
 
-            // Bob transfers tokens from Alice to Eve.
             assert_eq!(
-                erc20.transfer_from(accounts.alice, accounts.eve, 10),
+                PSP22::transfer(&mut erc20, accounts.bob, 10, Vec::new()),
                 Ok(())
             );
-            // Eve owns tokens.
-            assert_eq!(erc20.balance_of(accounts.eve), 10);
+            assert_eq!(PSP22::balance_of(&erc20, accounts.bob), 10);
+            assert_eq!(PSP22::total_supply(&erc20), 100);
+            assert_eq!(
+                PSP22::transfer(&mut erc20, accounts.bob, 91, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
 
-            // Check all transfer events that happened during the previous calls:
             let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            assert_eq!(emitted_events.len(), 3);
-            assert_transfer_event(
-                &emitted_events[0],
-                None,
-                Some(AccountId::from([0x01; 32])),
-                100,
-            );
-            // The second event `emitted_events[1]` is an Approve event that we skip
-            // checking.
+            assert_eq!(emitted_events.len(), 2);
             assert_transfer_event(
-                &emitted_events[2],
+                &emitted_events[1],
                 Some(AccountId::from([0x01; 32])),
-                Some(AccountId::from([0x05; 32])),
+                Some(AccountId::from([0x02; 32])),
                 10,
             );
         }
 
         #[ink::test]
-        fn allowance_must_not_change_on_failed_transfer() {
-            let mut erc20 = Erc20::new(100);
+        fn psp22_allowances_work() {
+            let mut erc20 = Erc20::new(100, None, None, None, 18);
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
 
-            // Alice approves Bob for token transfers on her behalf.
-            let alice_balance = erc20.balance_of(accounts.alice);
-            let initial_allowance = alice_balance + 2;
-            assert_eq!(erc20.approve(accounts.bob, initial_allowance), Ok(()));
+            assert_eq!(PSP22::approve(&mut erc20, accounts.bob, 10), Ok(()));
+            assert_eq!(
+                PSP22::increase_allowance(&mut erc20, accounts.bob, 5),
+                Ok(())
+            );
+            assert_eq!(
+                PSP22::decrease_allowance(&mut erc20, accounts.bob, 16),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                PSP22::decrease_allowance(&mut erc20, accounts.bob, 3),
+                Ok(())
+            );
+            assert_eq!(PSP22::allowance(&erc20, accounts.alice, accounts.bob), 12);
 
-            // Get contract address.
-            let callee = ink::env::account_id::<ink::env::DefaultEnvironment>();
-            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(callee);
             ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-
-            // Bob tries to transfer tokens from Alice to Eve.
-            let emitted_events_before = ink::env::test::recorded_events().count();
             assert_eq!(
-                erc20.transfer_from(accounts.alice, accounts.eve, alice_balance + 1),
-                Err(Error::InsufficientBalance)
+                PSP22::transfer_from(
+                    &mut erc20,
+                    accounts.alice,
+                    accounts.eve,
+                    13,
+                    Vec::new()
+                ),
+                Err(PSP22Error::InsufficientAllowance)
             );
-            // Allowance must have stayed the same
             assert_eq!(
-                erc20.allowance(accounts.alice, accounts.bob),
-                initial_allowance
+                PSP22::transfer_from(
+                    &mut erc20,
+                    accounts.alice,
+                    accounts.eve,
+                    12,
+                    Vec::new()
+                ),
+                Ok(())
             );
-            // No more events must have been emitted
-            assert_eq!(
-                emitted_events_before,
-                ink::env::test::recorded_events().count()
-            )
+            assert_eq!(erc20.balance_of(accounts.eve), 12);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
         }
 
         fn encoded_into_hash<T>(entity: T) -> Hash
@@ -528,7 +2550,7 @@ mod erc20 {
         async fn e2e_transfer<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
             // given
             let total_supply = 1_000_000_000;
-            let mut constructor = Erc20Ref::new(total_supply);
+            let mut constructor = Erc20Ref::new(total_supply, None, None, None, 18);
             let erc20 = client
                 .instantiate("erc20", &ink_e2e::alice(), &mut constructor)
                 .submit()
@@ -573,7 +2595,7 @@ mod erc20 {
         async fn e2e_allowances<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
             // given
             let total_supply = 1_000_000_000;
-            let mut constructor = Erc20Ref::new(total_supply);
+            let mut constructor = Erc20Ref::new(total_supply, None, None, None, 18);
             let erc20 = client
                 .instantiate("erc20", &ink_e2e::bob(), &mut constructor)
                 .submit()
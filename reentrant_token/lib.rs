@@ -0,0 +1,162 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+//! A token for the presale end-to-end tests that calls back into the presale
+//! whenever it moves tokens, the way a malicious token would.
+
+pub use self::reentrant_token::{
+    ReentrantToken,
+    ReentrantTokenRef,
+};
+
+#[ink::contract]
+mod reentrant_token {
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::storage::Mapping;
+
+    /// Token balances plus the presale project the token attacks.
+    #[ink(storage)]
+    pub struct ReentrantToken {
+        balances: Mapping<AccountId, Balance>,
+        /// Presale and project id the token calls back into, once armed.
+        target: Option<(AccountId, u32)>,
+        /// Number of callbacks made into the presale.
+        callbacks: u32,
+        /// Whether the presale accepted any of the callbacks.
+        reentered: bool,
+    }
+
+    /// Mirrors the first variant of `erc20::Error`, so `Erc20Ref` callers
+    /// decode it.
+    #[derive(Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Error {
+        InsufficientBalance,
+    }
+
+    impl ReentrantToken {
+        /// Creates `total_supply` tokens on the caller's account.
+        #[ink(constructor)]
+        pub fn new(total_supply: Balance) -> Self {
+            let mut balances = Mapping::default();
+            balances.insert(Self::env().caller(), &total_supply);
+            Self {
+                balances,
+                target: None,
+                callbacks: 0,
+                reentered: false,
+            }
+        }
+
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balances.get(owner).unwrap_or_default()
+        }
+
+        /// Moves tokens like `erc20::transfer`, then calls back.
+        #[ink(message)]
+        pub fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), Error> {
+            self.move_tokens(self.env().caller(), to, value)?;
+            self.call_back();
+            Ok(())
+        }
+
+        /// Moves tokens like `erc20::transfer_from`, without checking any
+        /// allowance, then calls back.
+        #[ink(message)]
+        pub fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<(), Error> {
+            self.move_tokens(from, to, value)?;
+            self.call_back();
+            Ok(())
+        }
+
+        /// Makes every later token move call `claim_tokens` and `claim_refund`
+        /// of `project_id` on `presale`.
+        #[ink(message)]
+        pub fn attack(&mut self, presale: AccountId, project_id: u32) {
+            self.target = Some((presale, project_id));
+        }
+
+        /// Returns the number of callbacks made into the presale.
+        #[ink(message)]
+        pub fn callbacks(&self) -> u32 {
+            self.callbacks
+        }
+
+        /// Returns whether the presale accepted any of the callbacks.
+        #[ink(message)]
+        pub fn reentered(&self) -> bool {
+            self.reentered
+        }
+
+        /// Joins `project_id` on `presale` with the transferred value, so the
+        /// token itself has something to claim.
+        #[ink(message, payable)]
+        pub fn join(&mut self, presale: AccountId, project_id: u32) {
+            build_call::<Environment>()
+                .call(presale)
+                .transferred_value(self.env().transferred_value())
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!(
+                        "join_project_presale"
+                    )))
+                    .push_arg(project_id)
+                    .push_arg(0 as Balance),
+                )
+                .returns::<()>()
+                .invoke();
+        }
+
+        /// Claims the token's own refund from `project_id` on `presale` and
+        /// returns whether the presale paid it.
+        #[ink(message)]
+        pub fn claim_refund(&mut self, presale: AccountId, project_id: u32) -> bool {
+            Self::claim(presale, ink::selector_bytes!("claim_refund"), project_id)
+        }
+
+        fn move_tokens(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<(), Error> {
+            let from_balance = self.balance_of(from);
+            if from_balance < value {
+                return Err(Error::InsufficientBalance)
+            }
+            self.balances.insert(from, &(from_balance - value));
+            let to_balance = self.balance_of(to);
+            self.balances.insert(to, &(to_balance + value));
+            Ok(())
+        }
+
+        fn call_back(&mut self) {
+            let Some((presale, project_id)) = self.target else {
+                return
+            };
+            for selector in [
+                ink::selector_bytes!("claim_tokens"),
+                ink::selector_bytes!("claim_refund"),
+            ] {
+                self.callbacks += 1;
+                self.reentered |= Self::claim(presale, selector, project_id);
+            }
+        }
+
+        /// Calls a claim message of the presale and returns whether it
+        /// succeeded.
+        fn claim(presale: AccountId, selector: [u8; 4], project_id: u32) -> bool {
+            // A rejected claim traps in the presale and comes back as an error.
+            build_call::<Environment>()
+                .call(presale)
+                .exec_input(ExecutionInput::new(Selector::new(selector)).push_arg(project_id))
+                .returns::<()>()
+                .try_invoke()
+                .is_ok_and(|result| result.is_ok())
+        }
+    }
+}
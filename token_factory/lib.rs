@@ -2,15 +2,178 @@
 
 #[ink::contract]
 mod token_factory {
+    use ink::codegen::TraitCallBuilder;
+    use ink::env::call::utils::{Argument, ArgumentList, EmptyArgumentList};
+    use ink::env::call::{build_create, ExecutionInput, FromAccountId, Selector};
+    use ink::env::hash::Blake2x256;
     use ink::prelude::string::String;
-    use ink::storage::Mapping;
-    use erc20::Erc20Ref;
+    use ink::prelude::vec::Vec;
+    use ink::scale::{Encode, Output};
+    use ink::storage::{Lazy, Mapping};
+    use ink::ToAccountId;
+    use bonding_curve_presale::BondingCurvePresaleRef;
+    use erc20::{Erc20Ref, Features, Role};
 
+    /// Factory storage layout this code expects. Version 2 replaced the single
+    /// `erc20_code_hash` with per-template code hashes.
+    const STORAGE_VERSION: u32 = 2;
+    /// Decimals of every token deployed from the `erc20` code.
+    const TOKEN_DECIMALS: u8 = 18;
+    /// Most entries a single listing call returns.
+    const MAX_PAGE_SIZE: u32 = 100;
+
+    /// The token factory error types.
+    #[derive(Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Error {
+        /// Returned if the caller is not the factory owner.
+        NotOwner,
+        /// Returned if less than `fee` was transferred with a creation call.
+        InsufficientFee,
+        /// Returned if more fees are withdrawn than were collected.
+        InsufficientCollectedFees,
+        /// Returned if the factory has no record of the requested token.
+        TokenNotFound,
+        /// Returned if no code hash is registered for the requested template.
+        TemplateNotFound,
+        /// Returned if `cap` is missing for a `Capped` token or set for another
+        /// template.
+        InvalidParams,
+        /// Returned if `launch` is called before the presale has been set.
+        PresaleNotSet,
+        /// Returned if instantiating the token contract failed.
+        InstantiationFailed,
+        /// Returned if moving tokens out of a freshly deployed token failed.
+        TokenTransferFailed,
+        /// Returned if handing the roles of a freshly deployed token over to
+        /// its creator failed.
+        RoleHandoverFailed,
+        /// Returned if the presale rejected the launched project.
+        PresaleRegistrationFailed,
+        /// Returned if sending native funds failed.
+        TransferFailed,
+        /// Returned if `set_code_hash` failed.
+        UpgradeFailed,
+        /// Returned if `migrate` is called on storage that is already current.
+        StorageUpToDate,
+    }
+
+    /// The token factory result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// The token contract variants the factory can deploy.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum TokenTemplate {
+        /// Fixed supply minted at construction.
+        Plain,
+        /// Supply can grow after construction.
+        Mintable,
+        /// Holders can burn their tokens, but the supply cannot grow.
+        Burnable,
+        /// Supply can grow, but never beyond a cap fixed at construction.
+        Capped,
+    }
+
+    impl TokenTemplate {
+        /// Returns the supply changes tokens of this template allow.
+        fn features(self) -> Features {
+            Features {
+                mintable: matches!(self, Self::Mintable | Self::Capped),
+                burnable: self == Self::Burnable,
+            }
+        }
+
+        /// Returns the roles `create_token` hands to the creator of a token of
+        /// this template.
+        ///
+        /// Only templates meant to mint after construction come with a
+        /// `Minter`; the others are deployed without one.
+        fn creator_roles(self) -> &'static [Role] {
+            match self {
+                Self::Mintable | Self::Capped => &[Role::Admin, Role::Minter, Role::Pauser],
+                Self::Plain | Self::Burnable => &[Role::Admin, Role::Pauser],
+            }
+        }
+    }
+
+    /// What a creator chooses for a new token.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct TokenParams {
+        pub name: String,
+        pub symbol: String,
+        pub initial_supply: Balance,
+        pub logo_uri: String,
+        /// Supply ceiling of a `Capped` token; must be `None` for the others.
+        pub cap: Option<Balance>,
+    }
+
+    /// Constructor arguments that are already SCALE encoded, so every
+    /// template is instantiated through the same `CreateBuilder`.
+    struct EncodedArgs(Vec<u8>);
+
+    impl Encode for EncodedArgs {
+        fn size_hint(&self) -> usize {
+            self.0.len()
+        }
+
+        fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(&self.0);
+        }
+    }
+
+    type ConstructorInput =
+        ExecutionInput<ArgumentList<Argument<EncodedArgs>, EmptyArgumentList>>;
+
+    /// What the factory records about each token it deployed.
+    #[derive(Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, PartialEq, Eq, ink::storage::traits::StorageLayout)
+    )]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct TokenInfo {
+        pub name: String,
+        pub symbol: String,
+        pub decimals: u8,
+        pub logo_uri: String,
+        pub creator: AccountId,
+        /// Block timestamp of the deployment.
+        pub created_at: u64,
+        pub initial_supply: Balance,
+    }
+
+    /// Live factories get new code through `upgrade` so that the token registry
+    /// and the uncollected fees stay where they are. Fields that later versions
+    /// added, such as `templates`, each live in their own `Mapping` or `Lazy`
+    /// cell, outside the packed root that first deployments wrote.
     #[ink(storage)]
     pub struct TokenFactory {
         tokens: Mapping<AccountId, Erc20Ref>,
+        /// Metadata of every token in `tokens`.
+        token_info: Mapping<AccountId, TokenInfo>,
         owner: AccountId,
         fee: Balance, // Fee for creating a token
+        storage_version: u32,
+        /// Code hash of the `erc20` contract deployed before templates
+        /// existed. `migrate` moves it to the `Plain` template.
+        erc20_code_hash: Lazy<Hash>,
+        /// Code hash each template is instantiated from.
+        templates: Mapping<TokenTemplate, Hash>,
+        /// Presale contract that `launch` registers projects with.
+        presale: Lazy<AccountId>,
+        /// Creation fees paid in and not yet withdrawn by the owner.
+        collected_fees: Lazy<Balance>,
+        /// Number of tokens in the registry.
+        token_count: Lazy<u32>,
+        /// Token addresses in deployment order.
+        token_by_index: Mapping<u32, AccountId>,
+        /// Number of tokens each creator has deployed.
+        creator_token_count: Mapping<AccountId, u32>,
+        /// Each creator's token addresses in deployment order.
+        token_by_creator_index: Mapping<(AccountId, u32), AccountId>,
     }
 
     impl TokenFactory {
@@ -19,43 +182,1437 @@ mod token_factory {
             let caller = Self::env().caller();
             Self {
                 tokens: Mapping::new(),
+                token_info: Mapping::new(),
                 owner: caller,
                 fee,
+                storage_version: STORAGE_VERSION,
+                erc20_code_hash: Lazy::new(),
+                templates: Mapping::new(),
+                presale: Lazy::new(),
+                collected_fees: Lazy::new(),
+                token_count: Lazy::new(),
+                token_by_index: Mapping::new(),
+                creator_token_count: Mapping::new(),
+                token_by_creator_index: Mapping::new(),
+            }
+        }
+
+        /// Returns the owner of the factory.
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        /// Returns the fee charged for creating a token.
+        #[ink(message)]
+        pub fn fee(&self) -> Balance {
+            self.fee
+        }
+
+        /// Returns the creation fees the owner can still withdraw.
+        #[ink(message)]
+        pub fn collected_fees(&self) -> Balance {
+            self.collected_fees.get().unwrap_or(0)
+        }
+
+        /// Changes the fee charged for creating a token.
+        ///
+        /// A `FeeChanged` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the owner.
+        #[ink(message)]
+        pub fn set_fee(&mut self, new_fee: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            let old_fee = self.fee;
+            self.fee = new_fee;
+            self.env().emit_event(FeeChanged { old_fee, new_fee });
+            Ok(())
+        }
+
+        /// Sends `amount` of the collected creation fees to `to`.
+        ///
+        /// A `FeesWithdrawn` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the owner.
+        ///
+        /// Returns `InsufficientCollectedFees` error if `amount` exceeds
+        /// `collected_fees`.
+        #[ink(message)]
+        pub fn withdraw_fees(&mut self, to: AccountId, amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            let collected = self.collected_fees();
+            if amount > collected {
+                return Err(Error::InsufficientCollectedFees)
+            }
+            self.collected_fees.set(&(collected - amount));
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| Error::TransferFailed)?;
+            self.env().emit_event(FeesWithdrawn { to, amount });
+            Ok(())
+        }
+
+        /// Returns the code hash `template` is instantiated from, if any.
+        #[ink(message)]
+        pub fn template_code_hash(&self, template: TokenTemplate) -> Option<Hash> {
+            self.templates.get(template)
+        }
+
+        /// Registers `code_hash` as the code of `template`, replacing any code
+        /// registered before. Tokens already deployed are not affected.
+        ///
+        /// A `TemplateAdded` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the owner.
+        #[ink(message)]
+        pub fn add_template(&mut self, template: TokenTemplate, code_hash: Hash) -> Result<()> {
+            self.ensure_owner()?;
+            self.templates.insert(template, &code_hash);
+            self.env().emit_event(TemplateAdded { template, code_hash });
+            Ok(())
+        }
+
+        /// Stops the factory from deploying `template`.
+        ///
+        /// A `TemplateRemoved` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the owner.
+        ///
+        /// Returns `TemplateNotFound` error if `template` is not registered.
+        #[ink(message)]
+        pub fn remove_template(&mut self, template: TokenTemplate) -> Result<()> {
+            self.ensure_owner()?;
+            if self.templates.take(template).is_none() {
+                return Err(Error::TemplateNotFound)
             }
+            self.env().emit_event(TemplateRemoved { template });
+            Ok(())
+        }
+
+        /// Sets the presale contract used by `launch`.
+        ///
+        /// The presale must list this factory as its launcher.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the owner.
+        #[ink(message)]
+        pub fn set_presale(&mut self, presale: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.presale.set(&presale);
+            Ok(())
         }
 
+        /// Returns the layout version of the data in storage.
         #[ink(message)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version
+        }
+
+        /// Replaces the code of the factory while keeping its storage.
+        ///
+        /// When the new code bumps `STORAGE_VERSION`, the owner must call
+        /// `migrate` right after the upgrade.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the owner.
+        ///
+        /// Returns `UpgradeFailed` error if no code is stored under `code_hash`.
+        #[ink(message)]
+        pub fn upgrade(&mut self, code_hash: Hash) -> Result<()> {
+            self.ensure_owner()?;
+            self.env()
+                .set_code_hash(&code_hash)
+                .map_err(|_| Error::UpgradeFailed)?;
+            self.env().emit_event(Upgraded { code_hash });
+            Ok(())
+        }
+
+        /// Brings the data in storage up to the layout of the running code.
+        ///
+        /// A `Migrated` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `NotOwner` error if the caller is not the owner.
+        ///
+        /// Returns `StorageUpToDate` error if there is nothing to migrate.
+        #[ink(message)]
+        pub fn migrate(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            let from_version = self.storage_version;
+            if from_version >= STORAGE_VERSION {
+                return Err(Error::StorageUpToDate)
+            }
+
+            // Each layout change adds a step here.
+            if self.storage_version == 1 {
+                if let Some(code_hash) = self.erc20_code_hash.get() {
+                    self.templates.insert(TokenTemplate::Plain, &code_hash);
+                }
+                self.storage_version = 2;
+            }
+
+            self.storage_version = STORAGE_VERSION;
+            self.env().emit_event(Migrated {
+                from_version,
+                to_version: STORAGE_VERSION,
+            });
+            Ok(())
+        }
+
+        /// Deploys a new token from `template`, sends its initial supply to the
+        /// caller and returns its address.
+        ///
+        /// The caller must pay at least `fee`; anything above it is refunded.
+        /// The token lands at `predict_token_address(caller, salt, template, params)`.
+        /// The caller becomes its admin and pauser, and also its minter for
+        /// `Mintable` and `Capped` tokens. A `TokenCreated` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientFee` error if less than `fee` was transferred.
+        ///
+        /// Returns `TemplateNotFound` or `InvalidParams` error if `template`
+        /// is not registered or does not fit `params`.
+        ///
+        /// Returns `InstantiationFailed` error if the token could not be
+        /// deployed, e.g. because the caller already used `salt` for the same
+        /// template and params.
+        #[ink(message, payable)]
         pub fn create_token(
             &mut self,
-            name: String,
-            symbol: String,
-            initial_supply: Balance,
-            logo_uri: String, // New parameter for logo URI
-        ) -> AccountId {
+            template: TokenTemplate,
+            params: TokenParams,
+            salt: [u8; 32],
+        ) -> Result<AccountId> {
             let caller = self.env().caller();
-            if caller != self.owner {
-                panic!("Only the owner can create tokens");
+            self.charge_fee(caller)?;
+
+            let roles = template.creator_roles();
+            let mut token = self.deploy_token(caller, salt, template, &params, roles)?;
+            let token_address = token.to_account_id();
+            token
+                .transfer(caller, params.initial_supply)
+                .map_err(|_| Error::TokenTransferFailed)?;
+            self.register_token(&token, caller, template, params);
+
+            Ok(token_address)
+        }
+
+        /// Deploys a new token, deposits its whole supply into the presale
+        /// contract and registers a presale for it in one transaction.
+        ///
+        /// The caller must pay at least `fee`; anything above it is refunded.
+        /// Returns the token address and the presale project id, and emits a
+        /// `TokenCreated` event. The token lands at
+        /// `predict_token_address(caller, salt, template, params)`.
+        ///
+        /// If the presale is cancelled or fails, the caller gets the supply
+        /// back through the presale's `reclaim_tokens`.
+        ///
+        /// Unlike `create_token`, the caller gets no role on the token: the
+        /// factory renounces them all, so nobody can pause the token and block
+        /// the presale's refunds and claims.
+        ///
+        /// # Errors
+        ///
+        /// Returns the errors of `create_token`, `PresaleNotSet` error if no
+        /// presale has been set and `PresaleRegistrationFailed` error if the
+        /// presale rejected the project.
+        #[ink(message, payable)]
+        pub fn launch(
+            &mut self,
+            template: TokenTemplate,
+            params: TokenParams,
+            start_time: u64,
+            end_time: u64,
+            salt: [u8; 32],
+        ) -> Result<(AccountId, u32)> {
+            let caller = self.env().caller();
+            self.charge_fee(caller)?;
+
+            let presale_address = self.presale.get().ok_or(Error::PresaleNotSet)?;
+            let mut token = self.deploy_token(caller, salt, template, &params, &[])?;
+            let token_address = token.to_account_id();
+
+            token
+                .transfer(presale_address, params.initial_supply)
+                .map_err(|_| Error::TokenTransferFailed)?;
+            let mut presale: BondingCurvePresaleRef =
+                FromAccountId::from_account_id(presale_address);
+            let project_id = presale
+                .call_mut()
+                .register_launch(
+                    token_address,
+                    caller,
+                    params.initial_supply,
+                    start_time,
+                    end_time,
+                )
+                .try_invoke()
+                .map_err(|_| Error::PresaleRegistrationFailed)?
+                .map_err(|_| Error::PresaleRegistrationFailed)?;
+
+            self.register_token(&token, caller, template, params);
+            Ok((token_address, project_id))
+        }
+
+        /// Returns the address the token `creator` deploys through
+        /// `create_token` or `launch` with `salt`, `template` and `params`
+        /// will get.
+        ///
+        /// Mirrors the pallet-contracts address derivation, which hashes the
+        /// deployer, the code hash, the constructor input and the salt, so
+        /// the template and the constructor arguments are part of the
+        /// prediction.
+        ///
+        /// # Errors
+        ///
+        /// Returns `TemplateNotFound` or `InvalidParams` error if `template`
+        /// is not registered or does not fit `params`.
+        #[ink(message)]
+        pub fn predict_token_address(
+            &self,
+            creator: AccountId,
+            salt: [u8; 32],
+            template: TokenTemplate,
+            params: TokenParams,
+        ) -> Result<AccountId> {
+            let code_hash = self.templates.get(template).ok_or(Error::TemplateNotFound)?;
+            let input = Self::constructor_input(template, &params)?.encode();
+            let salt = Self::creator_salt(creator, salt);
+
+            let mut address = [0u8; 32];
+            ink::env::hash_encoded::<Blake2x256, _>(
+                &(
+                    b"contract_addr_v1",
+                    self.env().account_id(),
+                    code_hash,
+                    &input[..],
+                    &salt[..],
+                ),
+                &mut address,
+            );
+            Ok(AccountId::from(address))
+        }
+
+        /// Returns what the factory recorded about `token_address`.
+        ///
+        /// # Errors
+        ///
+        /// Returns `TokenNotFound` error if the factory did not deploy it.
+        #[ink(message)]
+        pub fn get_token_info(&self, token_address: AccountId) -> Result<TokenInfo> {
+            self.token_info
+                .get(token_address)
+                .ok_or(Error::TokenNotFound)
+        }
+
+        /// Returns the number of tokens the factory has deployed.
+        #[ink(message)]
+        pub fn token_count(&self) -> u32 {
+            self.token_count.get().unwrap_or(0)
+        }
+
+        /// Returns up to `limit` token addresses in deployment order, skipping
+        /// the first `offset`. At most `MAX_PAGE_SIZE` are returned per call.
+        #[ink(message)]
+        pub fn list_tokens(&self, offset: u32, limit: u32) -> Vec<AccountId> {
+            Self::page(self.token_count(), offset, limit, |index| {
+                self.token_by_index.get(index)
+            })
+        }
+
+        /// Returns the number of tokens `creator` has deployed.
+        #[ink(message)]
+        pub fn creator_token_count(&self, creator: AccountId) -> u32 {
+            self.creator_token_count.get(creator).unwrap_or(0)
+        }
+
+        /// Returns up to `limit` of `creator`'s token addresses in deployment
+        /// order, skipping the first `offset`. At most `MAX_PAGE_SIZE` are
+        /// returned per call.
+        #[ink(message)]
+        pub fn tokens_by_creator(
+            &self,
+            creator: AccountId,
+            offset: u32,
+            limit: u32,
+        ) -> Vec<AccountId> {
+            Self::page(self.creator_token_count(creator), offset, limit, |index| {
+                self.token_by_creator_index.get((creator, index))
+            })
+        }
+
+        /// Keeps `fee` out of the transferred value and refunds the rest to
+        /// `payer`.
+        fn charge_fee(&mut self, payer: AccountId) -> Result<()> {
+            let transferred = self.env().transferred_value();
+            if transferred < self.fee {
+                return Err(Error::InsufficientFee)
             }
+            self.collected_fees.set(&(self.collected_fees() + self.fee));
 
-            let transferred_fee = self.env().transferred_value();
-            if transferred_fee < self.fee {
-                panic!("Insufficient fee");
+            let excess = transferred - self.fee;
+            if excess > 0 {
+                self.env()
+                    .transfer(payer, excess)
+                    .map_err(|_| Error::TransferFailed)?;
             }
+            Ok(())
+        }
 
-            let new_token = Erc20Ref::new(initial_supply);
-            // Get contract address.
-            let callee = ink::env::account_id::<ink::env::DefaultEnvironment>();
-            // let token_address = new_token.env().account_id();
-            let finalized_token = new_token.build(); 
-            self.tokens.insert(callee, finalized_token);
+        /// Instantiates the code of `template` under `creator`'s `salt`, hands
+        /// `roles` to `creator` and renounces every role the factory holds.
+        fn deploy_token(
+            &self,
+            creator: AccountId,
+            salt: [u8; 32],
+            template: TokenTemplate,
+            params: &TokenParams,
+            roles: &[Role],
+        ) -> Result<Erc20Ref> {
+            let code_hash = self.templates.get(template).ok_or(Error::TemplateNotFound)?;
+            let mut token = build_create::<Erc20Ref>()
+                .code_hash(code_hash)
+                .endowment(0)
+                .exec_input(Self::constructor_input(template, params)?)
+                .salt_bytes(Self::creator_salt(creator, salt))
+                .returns::<Erc20Ref>()
+                .try_instantiate()
+                .map_err(|_| Error::InstantiationFailed)?
+                .map_err(|_| Error::InstantiationFailed)?;
+            // `Admin` goes last, as granting needs it.
+            for role in [Role::Minter, Role::Pauser, Role::Admin] {
+                if roles.contains(&role) {
+                    token
+                        .grant_role(role, creator)
+                        .map_err(|_| Error::RoleHandoverFailed)?;
+                }
+                token
+                    .renounce_role(role)
+                    .map_err(|_| Error::RoleHandoverFailed)?;
+            }
+            Ok(token)
+        }
 
-            callee
+        /// Adds a freshly deployed token to the registry and its indexes.
+        fn register_token(
+            &mut self,
+            token: &Erc20Ref,
+            creator: AccountId,
+            template: TokenTemplate,
+            params: TokenParams,
+        ) {
+            let token_address = token.to_account_id();
+            self.tokens.insert(token_address, token);
+            self.env().emit_event(TokenCreated {
+                token: token_address,
+                creator,
+                template,
+                name: params.name.clone(),
+                symbol: params.symbol.clone(),
+                supply: params.initial_supply,
+            });
+            self.token_info.insert(
+                token_address,
+                &TokenInfo {
+                    name: params.name,
+                    symbol: params.symbol,
+                    decimals: TOKEN_DECIMALS,
+                    logo_uri: params.logo_uri,
+                    creator,
+                    created_at: self.env().block_timestamp(),
+                    initial_supply: params.initial_supply,
+                },
+            );
+
+            let index = self.token_count();
+            self.token_by_index.insert(index, &token_address);
+            self.token_count.set(&(index + 1));
+
+            let creator_index = self.creator_token_count(creator);
+            self.token_by_creator_index
+                .insert((creator, creator_index), &token_address);
+            self.creator_token_count.insert(creator, &(creator_index + 1));
         }
 
-        #[ink(message)]
-        pub fn get_token_info(&self, token_address: AccountId) -> (Balance) {
-            let token = self.tokens.get(&token_address).expect("Token not found");
-            (token.total_supply())
+        /// Returns the constructor call `template` is instantiated with.
+        ///
+        /// Every template goes through
+        /// `new_with_features(initial_supply, cap, features, ..)` followed by
+        /// the metadata arguments, with the template's `features`. Only
+        /// `Capped` tokens have a cap.
+        fn constructor_input(
+            template: TokenTemplate,
+            params: &TokenParams,
+        ) -> Result<ConstructorInput> {
+            let metadata = (
+                Some(params.name.clone()),
+                Some(params.symbol.clone()),
+                Some(params.logo_uri.clone()),
+                TOKEN_DECIMALS,
+            );
+            let cap = match (template, params.cap) {
+                (TokenTemplate::Capped, Some(cap)) => Some(cap),
+                (TokenTemplate::Capped, None) | (_, Some(_)) => {
+                    return Err(Error::InvalidParams)
+                }
+                (_, None) => None,
+            };
+            let args = (params.initial_supply, cap, template.features(), metadata).encode();
+            Ok(ExecutionInput::new(Selector::new(ink::selector_bytes!("new_with_features")))
+                .push_arg(EncodedArgs(args)))
+        }
+
+        /// Reads up to `limit` entries of an index, starting at `offset`.
+        fn page(
+            count: u32,
+            offset: u32,
+            limit: u32,
+            entry: impl Fn(u32) -> Option<AccountId>,
+        ) -> Vec<AccountId> {
+            let end = offset
+                .saturating_add(limit.min(MAX_PAGE_SIZE))
+                .min(count);
+            (offset..end).filter_map(entry).collect()
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner)
+            }
+            Ok(())
+        }
+
+        /// Binds `salt` to `creator`, so nobody can take the address another
+        /// creator has been shown for their salt.
+        fn creator_salt(creator: AccountId, salt: [u8; 32]) -> [u8; 32] {
+            let mut output = [0u8; 32];
+            ink::env::hash_encoded::<Blake2x256, _>(&(creator, salt), &mut output);
+            output
+        }
+    }
+
+    /// Event emitted when the factory deploys a token.
+    #[ink(event)]
+    pub struct TokenCreated {
+        #[ink(topic)]
+        token: AccountId,
+        #[ink(topic)]
+        creator: AccountId,
+        template: TokenTemplate,
+        name: String,
+        symbol: String,
+        supply: Balance,
+    }
+
+    /// Event emitted when the owner registers or replaces a template.
+    #[ink(event)]
+    pub struct TemplateAdded {
+        #[ink(topic)]
+        template: TokenTemplate,
+        code_hash: Hash,
+    }
+
+    /// Event emitted when the owner removes a template.
+    #[ink(event)]
+    pub struct TemplateRemoved {
+        #[ink(topic)]
+        template: TokenTemplate,
+    }
+
+    /// Event emitted when the owner changes the creation fee.
+    #[ink(event)]
+    pub struct FeeChanged {
+        old_fee: Balance,
+        new_fee: Balance,
+    }
+
+    /// Event emitted when the owner withdraws collected fees.
+    #[ink(event)]
+    pub struct FeesWithdrawn {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    /// Event emitted when the owner replaces the factory code.
+    #[ink(event)]
+    pub struct Upgraded {
+        #[ink(topic)]
+        code_hash: Hash,
+    }
+
+    /// Event emitted when `migrate` brings storage up to the running code.
+    #[ink(event)]
+    pub struct Migrated {
+        from_version: u32,
+        to_version: u32,
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        type Env = ink::env::DefaultEnvironment;
+
+        fn accounts() -> ink::env::test::DefaultAccounts<Env> {
+            ink::env::test::default_accounts::<Env>()
+        }
+
+        fn params(cap: Option<Balance>) -> TokenParams {
+            TokenParams {
+                name: "Token".into(),
+                symbol: "TKN".into(),
+                initial_supply: 1_000,
+                logo_uri: String::new(),
+                cap,
+            }
+        }
+
+        #[ink::test]
+        fn owner_sets_fee() {
+            let mut factory = TokenFactory::new(10);
+            assert_eq!(factory.set_fee(25), Ok(()));
+            assert_eq!(factory.fee(), 25);
+            assert_eq!(ink::env::test::recorded_events().count(), 1);
+        }
+
+        #[ink::test]
+        fn only_owner_sets_fee() {
+            let mut factory = TokenFactory::new(10);
+            ink::env::test::set_caller::<Env>(accounts().bob);
+            assert_eq!(factory.set_fee(0), Err(Error::NotOwner));
+            assert_eq!(factory.fee(), 10);
+        }
+
+        #[ink::test]
+        fn owner_withdraws_collected_fees() {
+            let accounts = accounts();
+            let mut factory = TokenFactory::new(10);
+            let contract = ink::env::test::callee::<Env>();
+            ink::env::test::set_account_balance::<Env>(contract, 30_000_000);
+            ink::env::test::set_account_balance::<Env>(accounts.eve, 0);
+            factory.collected_fees.set(&30_000_000);
+
+            assert_eq!(factory.withdraw_fees(accounts.eve, 20_000_000), Ok(()));
+
+            assert_eq!(factory.collected_fees(), 10_000_000);
+            assert_eq!(
+                ink::env::test::get_account_balance::<Env>(accounts.eve),
+                Ok(20_000_000)
+            );
+        }
+
+        #[ink::test]
+        fn cannot_withdraw_more_than_collected() {
+            let mut factory = TokenFactory::new(10);
+            assert_eq!(
+                factory.withdraw_fees(accounts().eve, 1),
+                Err(Error::InsufficientCollectedFees)
+            );
+        }
+
+        #[ink::test]
+        fn owner_only_settings_reject_others() {
+            let mut factory = TokenFactory::new(10);
+            ink::env::test::set_caller::<Env>(accounts().bob);
+            let hash = Hash::from([0x42; 32]);
+            assert_eq!(
+                factory.add_template(TokenTemplate::Plain, hash),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(
+                factory.remove_template(TokenTemplate::Plain),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(factory.set_presale(accounts().bob), Err(Error::NotOwner));
+            assert_eq!(factory.upgrade(hash), Err(Error::NotOwner));
+            assert_eq!(factory.migrate(), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn creating_without_fee_fails() {
+            let mut factory = TokenFactory::new(10);
+            assert_eq!(
+                factory.create_token(TokenTemplate::Plain, params(None), [0; 32]),
+                Err(Error::InsufficientFee)
+            );
+        }
+
+        #[ink::test]
+        fn launching_without_fee_fails() {
+            let mut factory = TokenFactory::new(10);
+            factory.set_presale(accounts().django).unwrap();
+            assert_eq!(
+                factory.launch(TokenTemplate::Plain, params(None), 1, 2, [0; 32]),
+                Err(Error::InsufficientFee)
+            );
+        }
+
+        #[ink::test]
+        fn launching_without_presale_fails() {
+            let mut factory = TokenFactory::new(0);
+            factory
+                .add_template(TokenTemplate::Plain, Hash::from([0x42; 32]))
+                .unwrap();
+            assert_eq!(
+                factory.launch(TokenTemplate::Plain, params(None), 1, 2, [0; 32]),
+                Err(Error::PresaleNotSet)
+            );
+        }
+
+        #[ink::test]
+        fn creating_from_missing_template_fails() {
+            let mut factory = TokenFactory::new(0);
+            assert_eq!(
+                factory.create_token(TokenTemplate::Plain, params(None), [0; 32]),
+                Err(Error::TemplateNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn owner_adds_and_removes_templates() {
+            let mut factory = TokenFactory::new(0);
+            let hash = Hash::from([0x42; 32]);
+
+            assert_eq!(factory.add_template(TokenTemplate::Capped, hash), Ok(()));
+            assert_eq!(factory.template_code_hash(TokenTemplate::Capped), Some(hash));
+            assert_eq!(factory.template_code_hash(TokenTemplate::Plain), None);
+
+            assert_eq!(factory.remove_template(TokenTemplate::Capped), Ok(()));
+            assert_eq!(factory.template_code_hash(TokenTemplate::Capped), None);
+            assert_eq!(
+                factory.remove_template(TokenTemplate::Capped),
+                Err(Error::TemplateNotFound)
+            );
+            assert_eq!(ink::env::test::recorded_events().count(), 2);
+        }
+
+        #[ink::test]
+        fn cap_must_match_template() {
+            let mut factory = TokenFactory::new(0);
+            let hash = Hash::from([0x42; 32]);
+            factory.add_template(TokenTemplate::Plain, hash).unwrap();
+            factory.add_template(TokenTemplate::Capped, hash).unwrap();
+
+            assert_eq!(
+                factory.create_token(TokenTemplate::Capped, params(None), [0; 32]),
+                Err(Error::InvalidParams)
+            );
+            assert_eq!(
+                factory.create_token(TokenTemplate::Plain, params(Some(2_000)), [0; 32]),
+                Err(Error::InvalidParams)
+            );
+        }
+
+        #[ink::test]
+        fn templates_differ_in_what_they_allow() {
+            let features = |template: TokenTemplate| {
+                let features = template.features();
+                (features.mintable, features.burnable)
+            };
+            assert_eq!(features(TokenTemplate::Plain), (false, false));
+            assert_eq!(features(TokenTemplate::Mintable), (true, false));
+            assert_eq!(features(TokenTemplate::Burnable), (false, true));
+            // Capped differs from Mintable by the cap it requires.
+            assert_eq!(features(TokenTemplate::Capped), (true, false));
+        }
+
+        #[ink::test]
+        fn migrate_moves_code_hash_to_plain_template() {
+            let mut factory = TokenFactory::new(0);
+            let hash = Hash::from([0x42; 32]);
+            factory.storage_version = 1;
+            factory.erc20_code_hash.set(&hash);
+
+            assert_eq!(factory.migrate(), Ok(()));
+
+            assert_eq!(factory.storage_version(), STORAGE_VERSION);
+            assert_eq!(factory.template_code_hash(TokenTemplate::Plain), Some(hash));
+            assert_eq!(factory.migrate(), Err(Error::StorageUpToDate));
+
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let migrated = <Migrated as ink::scale::Decode>::decode(
+                &mut &emitted_events.last().unwrap().data[..],
+            )
+            .expect("encountered invalid contract event data buffer");
+            assert_eq!(migrated.from_version, 1);
+            assert_eq!(migrated.to_version, STORAGE_VERSION);
+        }
+
+        #[ink::test]
+        fn predicted_address_depends_on_creator_salt_and_params() {
+            let accounts = accounts();
+            let mut factory = TokenFactory::new(0);
+            assert_eq!(
+                factory.predict_token_address(
+                    accounts.alice,
+                    [0; 32],
+                    TokenTemplate::Plain,
+                    params(None)
+                ),
+                Err(Error::TemplateNotFound)
+            );
+            let hash = Hash::from([0x42; 32]);
+            factory.add_template(TokenTemplate::Plain, hash).unwrap();
+            factory.add_template(TokenTemplate::Capped, hash).unwrap();
+
+            let predict = |creator, salt, template, params| {
+                factory
+                    .predict_token_address(creator, salt, template, params)
+                    .unwrap()
+            };
+            let plain = TokenTemplate::Plain;
+            let address = predict(accounts.alice, [0; 32], plain, params(None));
+            assert_eq!(address, predict(accounts.alice, [0; 32], plain, params(None)));
+            assert_ne!(address, predict(accounts.bob, [0; 32], plain, params(None)));
+            assert_ne!(address, predict(accounts.alice, [1; 32], plain, params(None)));
+            let more = TokenParams {
+                initial_supply: 1_001,
+                ..params(None)
+            };
+            assert_ne!(address, predict(accounts.alice, [0; 32], plain, more));
+            assert_ne!(
+                address,
+                predict(accounts.alice, [0; 32], TokenTemplate::Capped, params(Some(1_000)))
+            );
+        }
+
+        /// Records a token numbered `index` for `creator`, as `create_token`
+        /// does once the token is deployed.
+        fn register(factory: &mut TokenFactory, index: u32, creator: AccountId) -> AccountId {
+            let mut address = [0xAA; 32];
+            address[..4].copy_from_slice(&index.to_le_bytes());
+            let token = AccountId::from(address);
+            factory.register_token(
+                &FromAccountId::from_account_id(token),
+                creator,
+                TokenTemplate::Plain,
+                params(None),
+            );
+            token
+        }
+
+        #[ink::test]
+        fn list_tokens_pages_in_deployment_order() {
+            let accounts = accounts();
+            let mut factory = TokenFactory::new(0);
+            assert!(factory.list_tokens(0, 10).is_empty());
+            let tokens: Vec<AccountId> = (0..5)
+                .map(|index| register(&mut factory, index, accounts.alice))
+                .collect();
+
+            assert_eq!(factory.token_count(), 5);
+            assert_eq!(factory.list_tokens(0, 10), tokens);
+            assert_eq!(factory.list_tokens(1, 2), tokens[1..3]);
+            assert_eq!(factory.list_tokens(4, 10), tokens[4..]);
+            assert!(factory.list_tokens(0, 0).is_empty());
+            assert!(factory.list_tokens(5, 10).is_empty());
+            assert!(factory.list_tokens(100, 10).is_empty());
+            assert!(factory.list_tokens(u32::MAX, u32::MAX).is_empty());
+        }
+
+        #[ink::test]
+        fn listings_are_clamped_to_max_page_size() {
+            let accounts = accounts();
+            let mut factory = TokenFactory::new(0);
+            let tokens: Vec<AccountId> = (0..MAX_PAGE_SIZE + 5)
+                .map(|index| register(&mut factory, index, accounts.alice))
+                .collect();
+            let page_size = MAX_PAGE_SIZE as usize;
+
+            assert_eq!(factory.list_tokens(0, u32::MAX), tokens[..page_size]);
+            assert_eq!(factory.list_tokens(MAX_PAGE_SIZE, u32::MAX), tokens[page_size..]);
+            assert_eq!(
+                factory.tokens_by_creator(accounts.alice, 0, MAX_PAGE_SIZE + 1),
+                tokens[..page_size]
+            );
+        }
+
+        #[ink::test]
+        fn tokens_by_creator_keeps_each_creators_order() {
+            let accounts = accounts();
+            let mut factory = TokenFactory::new(0);
+            let tokens: Vec<AccountId> = (0..5)
+                .map(|index| {
+                    let creator = if index % 2 == 0 {
+                        accounts.alice
+                    } else {
+                        accounts.bob
+                    };
+                    register(&mut factory, index, creator)
+                })
+                .collect();
+
+            assert_eq!(factory.creator_token_count(accounts.alice), 3);
+            assert_eq!(
+                factory.tokens_by_creator(accounts.alice, 0, 10),
+                [tokens[0], tokens[2], tokens[4]]
+            );
+            assert_eq!(factory.tokens_by_creator(accounts.alice, 1, 1), [tokens[2]]);
+            assert_eq!(
+                factory.tokens_by_creator(accounts.bob, 0, 10),
+                [tokens[1], tokens[3]]
+            );
+            assert!(factory.tokens_by_creator(accounts.bob, 2, 10).is_empty());
+            assert_eq!(factory.creator_token_count(accounts.charlie), 0);
+            assert!(factory.tokens_by_creator(accounts.charlie, 0, 10).is_empty());
+            assert_eq!(
+                factory.get_token_info(tokens[3]).map(|info| info.creator),
+                Ok(accounts.bob)
+            );
+        }
+
+        #[ink::test]
+        fn unknown_token_info_is_an_error() {
+            let factory = TokenFactory::new(0);
+            assert_eq!(
+                factory.get_token_info(accounts().bob),
+                Err(Error::TokenNotFound)
+            );
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use bonding_curve_presale::BondingCurvePresale;
+        use ink_e2e::{ContractsBackend, E2EBackend};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn launched_supply_returns_to_creator_on_cancel<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let (factory, presale) =
+                factory_with_presale(&mut client, TokenTemplate::Plain).await;
+            let mut call_builder = ink_e2e::create_call_builder::<TokenFactory>(factory);
+            let mut presale_calls = ink_e2e::create_call_builder::<BondingCurvePresale>(presale);
+
+            let params = TokenParams {
+                name: "Token".into(),
+                symbol: "TKN".into(),
+                initial_supply: 1_000,
+                logo_uri: String::new(),
+                cap: None,
+            };
+            let launch = call_builder.launch(
+                TokenTemplate::Plain,
+                params,
+                u64::MAX - 1,
+                u64::MAX,
+                [7; 32],
+            );
+            let (token, project_id) = client
+                .call(&ink_e2e::alice(), &launch)
+                .submit()
+                .await
+                .expect("launch failed")
+                .return_value()
+                .expect("launch returned an error");
+            let token_calls = ink_e2e::create_call_builder::<erc20::Erc20>(token);
+            let deposited = client
+                .call(&ink_e2e::alice(), &token_calls.balance_of(presale))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(deposited, 1_000, "supply was not deposited into the presale");
+            let project = client
+                .call(&ink_e2e::alice(), &presale_calls.get_project(project_id))
+                .dry_run()
+                .await?
+                .return_value();
+            assert!(project.is_some(), "presale project was not registered");
+
+            // when
+            client
+                .call(&ink_e2e::alice(), &presale_calls.cancel_presale(project_id))
+                .submit()
+                .await
+                .expect("cancel_presale failed");
+            let reclaim = presale_calls.reclaim_tokens(project_id);
+            client
+                .call(&ink_e2e::alice(), &reclaim)
+                .submit()
+                .await
+                .expect("reclaim_tokens failed");
+            let again = client.call(&ink_e2e::alice(), &reclaim).submit().await;
+
+            // then
+            let returned = client
+                .call(&ink_e2e::alice(), &token_calls.balance_of(alice))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(returned, 1_000, "supply did not return to the creator");
+            assert!(again.is_err(), "supply was returned twice");
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn launched_token_creator_cannot_block_claims<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let (factory, presale) =
+                factory_with_presale(&mut client, TokenTemplate::Mintable).await;
+            let mut call_builder = ink_e2e::create_call_builder::<TokenFactory>(factory);
+            let mut presale_calls = ink_e2e::create_call_builder::<BondingCurvePresale>(presale);
+
+            // Bob's payment buys the half of the supply that is for sale.
+            let params = TokenParams {
+                name: "Token".into(),
+                symbol: "TKN".into(),
+                initial_supply: 1_000_000_000_000,
+                logo_uri: String::new(),
+                cap: None,
+            };
+            let start = now_millis() + 10_000;
+            let end = start + 20_000;
+            let launch = call_builder.launch(TokenTemplate::Mintable, params, start, end, [7; 32]);
+            let (token, project_id) = client
+                .call(&ink_e2e::alice(), &launch)
+                .submit()
+                .await
+                .expect("launch failed")
+                .return_value()
+                .expect("launch returned an error");
+            let mut token_calls = ink_e2e::create_call_builder::<erc20::Erc20>(token);
+
+            // when
+            for role in [Role::Admin, Role::Minter, Role::Pauser] {
+                let held = client
+                    .call(&ink_e2e::alice(), &token_calls.has_role(role, alice))
+                    .dry_run()
+                    .await?
+                    .return_value();
+                assert!(!held, "creator holds {role:?}");
+            }
+            let paused = client
+                .call(&ink_e2e::alice(), &token_calls.pause())
+                .dry_run()
+                .await?
+                .return_value();
+            let granted = client
+                .call(&ink_e2e::alice(), &token_calls.grant_role(Role::Pauser, alice))
+                .dry_run()
+                .await?
+                .return_value();
+
+            sleep_until(start);
+            let join = presale_calls.join_project_presale(project_id, 0);
+            client
+                .call(&ink_e2e::bob(), &join)
+                .value(5_000_000_000)
+                .submit()
+                .await
+                .expect("join_project_presale failed");
+            sleep_until(end);
+            client
+                .call(&ink_e2e::bob(), &presale_calls.claim_tokens(project_id))
+                .submit()
+                .await
+                .expect("claim_tokens failed");
+
+            // then
+            assert_eq!(paused, Err(erc20::Error::MissingRole));
+            assert_eq!(granted, Err(erc20::Error::MissingRole));
+            let claimed = client
+                .call(&ink_e2e::bob(), &token_calls.balance_of(bob))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(claimed, 500_000_000_000, "tokens were not claimed");
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn tokens_land_at_predicted_address<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let mut constructor = TokenFactoryRef::new(0);
+            let factory = client
+                .instantiate("token_factory", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = factory.call_builder::<TokenFactory>();
+
+            let code_hash = client
+                .upload("erc20", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+            let code_hash: [u8; 32] = code_hash.as_ref().try_into().unwrap();
+            let add_template =
+                call_builder.add_template(TokenTemplate::Plain, Hash::from(code_hash));
+            client
+                .call(&ink_e2e::alice(), &add_template)
+                .submit()
+                .await
+                .expect("add_template failed");
+
+            let salt = [7; 32];
+            let params = TokenParams {
+                name: "Token".into(),
+                symbol: "TKN".into(),
+                initial_supply: 1_000,
+                logo_uri: String::new(),
+                cap: None,
+            };
+            let predict = call_builder.predict_token_address(
+                alice,
+                salt,
+                TokenTemplate::Plain,
+                params.clone(),
+            );
+            let predicted = client
+                .call(&ink_e2e::alice(), &predict)
+                .dry_run()
+                .await?
+                .return_value()
+                .expect("prediction failed");
+
+            // when
+            let create = call_builder.create_token(TokenTemplate::Plain, params, salt);
+            let created = client
+                .call(&ink_e2e::alice(), &create)
+                .submit()
+                .await
+                .expect("create_token failed")
+                .return_value()
+                .expect("create_token returned an error");
+
+            // then
+            assert_eq!(created, predicted, "token deployed to another address");
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn storage_survives_upgrade<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given
+            let mut constructor = TokenFactoryRef::new(5);
+            let factory = client
+                .instantiate("token_factory", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = factory.call_builder::<TokenFactory>();
+
+            let template_hash = Hash::from([0x42; 32]);
+            let add_template = call_builder.add_template(TokenTemplate::Mintable, template_hash);
+            client
+                .call(&ink_e2e::alice(), &add_template)
+                .submit()
+                .await
+                .expect("add_template failed");
+
+            // when
+            let code_hash = client
+                .upload("token_factory", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+            let code_hash: [u8; 32] = code_hash.as_ref().try_into().unwrap();
+            let upgrade = call_builder.upgrade(Hash::from(code_hash));
+            client
+                .call(&ink_e2e::alice(), &upgrade)
+                .submit()
+                .await
+                .expect("upgrade failed")
+                .return_value()
+                .expect("upgrade returned an error");
+
+            // then
+            let fee = client
+                .call(&ink_e2e::alice(), &call_builder.fee())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(fee, 5, "fee changed across the upgrade");
+            let template = client
+                .call(
+                    &ink_e2e::alice(),
+                    &call_builder.template_code_hash(TokenTemplate::Mintable),
+                )
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(template, Some(template_hash), "template lost in the upgrade");
+            let version = client
+                .call(&ink_e2e::alice(), &call_builder.storage_version())
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(version, STORAGE_VERSION);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn plain_token_creator_gets_no_minter<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            let roles = roles_after_creation(&mut client, TokenTemplate::Plain).await;
+            assert_eq!(roles, [(true, false), (false, false), (true, false)]);
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn plain_token_creator_cannot_grant_themselves_minter<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let (token, _) = create_from_template(&mut client, TokenTemplate::Plain).await;
+            let mut token_calls = ink_e2e::create_call_builder::<erc20::Erc20>(token);
+
+            let granted = client
+                .call(&ink_e2e::alice(), &token_calls.grant_role(Role::Minter, alice))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(granted, Err(erc20::Error::NotMintable));
+            let minted = client
+                .call(&ink_e2e::alice(), &token_calls.mint(alice, 1))
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(minted, Err(erc20::Error::NotMintable));
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn mintable_token_creator_gets_every_role<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            let roles = roles_after_creation(&mut client, TokenTemplate::Mintable).await;
+            assert_eq!(roles, [(true, false), (true, false), (true, false)]);
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn burnable_token_creator_gets_no_minter<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            let roles = roles_after_creation(&mut client, TokenTemplate::Burnable).await;
+            assert_eq!(roles, [(true, false), (false, false), (true, false)]);
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn capped_token_creator_gets_every_role<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            let roles = roles_after_creation(&mut client, TokenTemplate::Capped).await;
+            assert_eq!(roles, [(true, false), (true, false), (true, false)]);
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn templates_turn_mint_and_burn_on_or_off<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            for (template, mintable, burnable) in [
+                (TokenTemplate::Plain, false, false),
+                (TokenTemplate::Mintable, true, false),
+                (TokenTemplate::Burnable, false, true),
+                (TokenTemplate::Capped, true, false),
+            ] {
+                let (token, _) = create_from_template(&mut client, template).await;
+                let mut token_calls = ink_e2e::create_call_builder::<erc20::Erc20>(token);
+                let features = client
+                    .call(&ink_e2e::alice(), &token_calls.features())
+                    .dry_run()
+                    .await?
+                    .return_value();
+                assert_eq!(features, Features { mintable, burnable }, "{template:?}");
+                let burnt = client
+                    .call(&ink_e2e::alice(), &token_calls.burn(1))
+                    .dry_run()
+                    .await?
+                    .return_value();
+                assert_eq!(burnt.is_ok(), burnable, "{template:?}");
+            }
+            Ok(())
+        }
+
+        fn now_millis() -> u64 {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("clock before 1970")
+                .as_millis() as u64
+        }
+
+        /// Waits until a block after `timestamp` can be produced.
+        fn sleep_until(timestamp: u64) {
+            std::thread::sleep(std::time::Duration::from_millis(
+                timestamp.saturating_sub(now_millis()) + 1_000,
+            ));
+        }
+
+        /// Deploys a factory serving `template` from the `erc20` code and a
+        /// presale it launches into, both owned by Alice, and returns their
+        /// addresses.
+        async fn factory_with_presale<Client>(
+            client: &mut Client,
+            template: TokenTemplate,
+        ) -> (AccountId, AccountId)
+        where
+            Client: E2EBackend,
+            <Client as ContractsBackend<ink::env::DefaultEnvironment>>::Error:
+                core::fmt::Debug,
+        {
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let mut constructor = TokenFactoryRef::new(0);
+            let factory = client
+                .instantiate("token_factory", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = factory.call_builder::<TokenFactory>();
+            let mut constructor = BondingCurvePresaleRef::new(alice, 0);
+            let presale = client
+                .instantiate("bonding_curve_presale", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("presale instantiate failed");
+            let mut presale_calls = presale.call_builder::<BondingCurvePresale>();
+
+            let code_hash = client
+                .upload("erc20", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+            let code_hash: [u8; 32] = code_hash.as_ref().try_into().unwrap();
+            client
+                .call(
+                    &ink_e2e::alice(),
+                    &call_builder.add_template(template, Hash::from(code_hash)),
+                )
+                .submit()
+                .await
+                .expect("add_template failed");
+            client
+                .call(
+                    &ink_e2e::alice(),
+                    &presale_calls.set_launcher(Some(factory.account_id)),
+                )
+                .submit()
+                .await
+                .expect("set_launcher failed");
+            client
+                .call(&ink_e2e::alice(), &call_builder.set_presale(presale.account_id))
+                .submit()
+                .await
+                .expect("set_presale failed");
+            (factory.account_id, presale.account_id)
+        }
+
+        /// Creates a token from `template` as Alice through a new factory and
+        /// returns the addresses of the token and the factory.
+        async fn create_from_template<Client>(
+            client: &mut Client,
+            template: TokenTemplate,
+        ) -> (AccountId, AccountId)
+        where
+            Client: E2EBackend,
+            <Client as ContractsBackend<ink::env::DefaultEnvironment>>::Error:
+                core::fmt::Debug,
+        {
+            let mut constructor = TokenFactoryRef::new(0);
+            let factory = client
+                .instantiate("token_factory", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = factory.call_builder::<TokenFactory>();
+
+            let code_hash = client
+                .upload("erc20", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+            let code_hash: [u8; 32] = code_hash.as_ref().try_into().unwrap();
+            let add_template = call_builder.add_template(template, Hash::from(code_hash));
+            client
+                .call(&ink_e2e::alice(), &add_template)
+                .submit()
+                .await
+                .expect("add_template failed");
+
+            let params = TokenParams {
+                name: "Token".into(),
+                symbol: "TKN".into(),
+                initial_supply: 1_000,
+                logo_uri: String::new(),
+                cap: (template == TokenTemplate::Capped).then_some(2_000),
+            };
+            let create = call_builder.create_token(template, params, [7; 32]);
+            let token = client
+                .call(&ink_e2e::alice(), &create)
+                .submit()
+                .await
+                .expect("create_token failed")
+                .return_value()
+                .expect("create_token returned an error");
+            (token, factory.account_id)
+        }
+
+        /// Creates a token from `template` as Alice and returns, for `Admin`,
+        /// `Minter` and `Pauser`, whether Alice and whether the factory hold
+        /// the role.
+        async fn roles_after_creation<Client>(
+            client: &mut Client,
+            template: TokenTemplate,
+        ) -> [(bool, bool); 3]
+        where
+            Client: E2EBackend,
+            <Client as ContractsBackend<ink::env::DefaultEnvironment>>::Error:
+                core::fmt::Debug,
+        {
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let (token, factory) = create_from_template(client, template).await;
+            let token_calls = ink_e2e::create_call_builder::<erc20::Erc20>(token);
+            let mut roles = [(false, false); 3];
+            for (held, role) in roles.iter_mut().zip([Role::Admin, Role::Minter, Role::Pauser]) {
+                for (holds, account) in [(&mut held.0, alice), (&mut held.1, factory)] {
+                    *holds = client
+                        .call(&ink_e2e::alice(), &token_calls.has_role(role, account))
+                        .dry_run()
+                        .await
+                        .expect("has_role failed")
+                        .return_value();
+                }
+            }
+            roles
         }
     }
 }